@@ -0,0 +1,360 @@
+use crate::board::{random_moves, Board, Move};
+use crate::dataset::DatasetError;
+use crate::eval::{DefaultEvaluator, EvalWeights};
+use crate::limits::SearchLimits;
+use crate::minmax::{available_cores, SearchConfig, Searcher};
+use crate::selfplay::{game_result, play_games};
+use std::fs;
+use std::path::Path;
+
+/// One side of a match.
+pub struct EngineConfig {
+    pub search: SearchConfig,
+    pub limits: SearchLimits,
+    pub weights: EvalWeights,
+}
+
+impl Default for EngineConfig {
+    fn default() -> EngineConfig {
+        EngineConfig { search: SearchConfig::deterministic(), limits: SearchLimits::depth(4), weights: EvalWeights::default() }
+    }
+}
+
+/// Sequential probability ratio test between H0 : the first engine is
+/// `elo0` stronger, and H1 : it is `elo1` stronger.
+#[derive(PartialEq, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Chance of accepting H1 when H0 holds.
+    pub alpha: f64,
+    /// Chance of accepting H0 when H1 holds.
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    /// The log-likelihood ratio bounds : below the first, H0 is accepted,
+    /// above the second, H1 is.
+    pub fn bounds(&self) -> (f64, f64) {
+        return ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln());
+    }
+}
+
+impl Copy for Sprt {}
+
+impl Clone for Sprt {
+    fn clone(&self) -> Sprt {
+        *self
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum SprtDecision {
+    Continue,
+    AcceptH0,
+    AcceptH1,
+}
+
+pub struct MatchConfig {
+    /// Games to play, unless the SPRT stops the match before.
+    pub games: usize,
+    /// Games played at the same time.
+    pub parallel: usize,
+    /// Each opening is played twice, each engine moving first once. Without
+    /// any, the openings are random.
+    pub openings: Vec<Vec<Move>>,
+    /// Length of the random openings.
+    pub random_plies: usize,
+    pub seed: u64,
+    /// As in `SelfPlayConfig`.
+    pub max_plies: usize,
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> MatchConfig {
+        MatchConfig {
+            games: 100,
+            parallel: available_cores(),
+            openings: Vec::new(),
+            random_plies: 6,
+            seed: 1,
+            max_plies: 300,
+            sprt: None,
+        }
+    }
+}
+
+/// Results of the first engine.
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        return self.wins + self.draws + self.losses;
+    }
+
+    pub fn add(&mut self, result: f64) {
+        if result > 0.75 {
+            self.wins += 1;
+        } else if result < 0.25 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    /// Mean points per game of the first engine.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        return (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64;
+    }
+
+    /// Variance of the points of one game.
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games().max(1) as f64;
+        return (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2))
+            / games;
+    }
+
+    /// Elo difference of the first engine over the second.
+    pub fn elo(&self) -> f64 {
+        return score_to_elo(self.score());
+    }
+
+    /// Half-width of the 95% confidence interval of `elo`.
+    pub fn elo_error(&self) -> f64 {
+        let games = self.games().max(1) as f64;
+        let margin = 1.96 * (self.variance() / games).sqrt();
+        let score = self.score();
+        return (score_to_elo(score + margin) - score_to_elo(score - margin)) / 2.0;
+    }
+
+    /// Log-likelihood ratio of H1 over H0, in the normal approximation.
+    pub fn llr(&self, sprt: &Sprt) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (score0, score1) = (elo_to_score(sprt.elo0), elo_to_score(sprt.elo1));
+        let games = self.games() as f64;
+        return (score1 - score0) * (2.0 * self.score() - score0 - score1) * games / (2.0 * variance);
+    }
+
+    pub fn sprt(&self, sprt: &Sprt) -> SprtDecision {
+        let llr = self.llr(sprt);
+        let (lower, upper) = sprt.bounds();
+        if llr <= lower {
+            return SprtDecision::AcceptH0;
+        }
+        if llr >= upper {
+            return SprtDecision::AcceptH1;
+        }
+        return SprtDecision::Continue;
+    }
+}
+
+impl Copy for MatchStats {}
+
+impl Clone for MatchStats {
+    fn clone(&self) -> MatchStats {
+        *self
+    }
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+    return 1.0 / (1.0 + 10f64.powf(-elo / 400.0));
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    return 400.0 * (score / (1.0 - score)).log10();
+}
+
+#[derive(PartialEq, Debug)]
+pub struct MatchGame {
+    /// Number of the game, from 0 : the even ones are played by the first
+    /// engine as first player.
+    pub index: usize,
+    pub moves: Vec<Move>,
+    /// Plies of `moves` which come from the opening.
+    pub opening_plies: usize,
+    /// Points of the first engine : 1, 0.5 or 0.
+    pub result: f64,
+}
+
+impl MatchGame {
+    /// Player (0 or 1) of the first engine in this game.
+    pub fn first_engine_player(&self) -> usize {
+        return self.index % 2;
+    }
+}
+
+/// The searchers of both engines, as used by one thread.
+type Players<'a> = [(Searcher, &'a SearchLimits); 2];
+
+fn new_players<'a>(engines: [&'a EngineConfig; 2]) -> Players<'a> {
+    return engines.map(|engine| (Searcher::with_evaluator(engine.search, DefaultEvaluator::new(engine.weights)), &engine.limits));
+}
+
+/// Plays game number `index`, from the opening of its pair.
+fn play_match_game(players: &Players, config: &MatchConfig, index: usize) -> MatchGame {
+    let pair = index / 2;
+    let opening = if config.openings.is_empty() {
+        random_moves(config.seed.wrapping_add(pair as u64), config.random_plies)
+    } else {
+        config.openings[pair % config.openings.len()].clone()
+    };
+
+    let first_engine_player = index % 2;
+    let mut board = Board::new();
+    let mut player = 0;
+    for &mv in &opening {
+        board.make_move(player, mv);
+        player = (player + 1) % 2;
+    }
+    for (searcher, _) in players {
+        searcher.clear();
+    }
+
+    let mut moves = opening.clone();
+    let mut limits = [*players[0].1, *players[1].1];
+    while !board.is_final_position() && moves.len() < config.max_plies {
+        let engine = if player == first_engine_player { 0 } else { 1 };
+        let result = players[engine].0.search(&board, player, &limits[engine]);
+        if let Some(clock) = &mut limits[engine].clock {
+            clock.spend(result.elapsed);
+        }
+        let mv = result.best_move.expect("an unfinished game has legal moves");
+        board.make_move(player, mv);
+        moves.push(mv);
+        player = (player + 1) % 2;
+    }
+
+    let result = game_result(&board);
+    let result = if first_engine_player == 0 { result } else { 1.0 - result };
+    return MatchGame { index, moves, opening_plies: opening.len(), result };
+}
+
+/// Plays the first engine against the second, `config.parallel` games at a
+/// time, until `config.games` are played or the SPRT decides. `on_game` is
+/// called by the calling thread after each game with the updated stats.
+pub fn run_match(first: &EngineConfig, second: &EngineConfig, config: &MatchConfig, on_game: &mut dyn FnMut(&MatchGame, &MatchStats)) -> MatchStats {
+    let mut stats = MatchStats::default();
+    play_games(
+        config.games,
+        config.parallel,
+        || new_players([first, second]),
+        |players, index| play_match_game(players, config, index),
+        // Games finished after the decision do not count.
+        &mut |game: MatchGame| {
+            stats.add(game.result);
+            on_game(&game, &stats);
+            return !config.sprt.is_some_and(|sprt| stats.sprt(&sprt) != SprtDecision::Continue);
+        },
+    );
+    return stats;
+}
+
+/// One opening per line, the moves from the start ; `#` starts a comment.
+pub fn parse_openings(text: &str) -> Result<Vec<Vec<Move>>, DatasetError> {
+    let mut openings = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let opening = parse_opening(line).map_err(|error| DatasetError::Line(index + 1, Box::new(error)))?;
+        openings.push(opening);
+    }
+    return Ok(openings);
+}
+
+fn parse_opening(line: &str) -> Result<Vec<Move>, DatasetError> {
+    let mut board = Board::new();
+    let mut player = 0;
+    let mut opening = Vec::new();
+    for mv in line.split_whitespace() {
+        let mv = mv.parse::<Move>().map_err(|_| DatasetError::Syntax(mv.to_string()))?;
+        board.try_play_move(player, mv).map_err(DatasetError::IllegalMove)?;
+        opening.push(mv);
+        player = (player + 1) % 2;
+    }
+    return Ok(opening);
+}
+
+pub fn read_openings(path: &Path) -> Result<Vec<Vec<Move>>, DatasetError> {
+    return parse_openings(&fs::read_to_string(path)?);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_stats() {
+        let even = MatchStats { wins: 30, draws: 40, losses: 30 };
+        assert_eq!(100, even.games());
+        assert_eq!(0.5, even.score());
+        assert!(even.elo().abs() < 1e-9);
+        assert!(even.elo_error() > 40.0 && even.elo_error() < 70.0);
+
+        // 75% is about +191 Elo.
+        let better = MatchStats { wins: 60, draws: 30, losses: 10 };
+        assert!((better.elo() - 190.8).abs() < 0.1);
+        assert!(better.elo_error() < 2.0 * even.elo_error());
+
+        let sprt = Sprt::new(0.0, 10.0);
+        assert!(even.llr(&sprt) < 0.0);
+        assert!(better.llr(&sprt) > 0.0);
+        assert_eq!(SprtDecision::AcceptH1, better.sprt(&sprt));
+        assert_eq!(SprtDecision::Continue, MatchStats { wins: 3, draws: 2, losses: 2 }.sprt(&sprt));
+        let worse = MatchStats { wins: 10, draws: 30, losses: 60 };
+        assert_eq!(SprtDecision::AcceptH0, worse.sprt(&sprt));
+    }
+
+    #[test]
+    fn test_run_match() {
+        let config = MatchConfig {
+            games: 4,
+            parallel: 2,
+            openings: parse_openings("# two openings\n1R 14B\n\n3B # short\n").unwrap(),
+            ..MatchConfig::default()
+        };
+        let first = EngineConfig { limits: SearchLimits::depth(3), ..EngineConfig::default() };
+        let second = EngineConfig { limits: SearchLimits::depth(1), ..EngineConfig::default() };
+
+        let mut games = Vec::new();
+        let stats = run_match(&first, &second, &config, &mut |game, _| games.push(game.moves.clone()));
+        assert_eq!(4, stats.games());
+        assert_eq!(4, games.len());
+        assert!(games.iter().all(|moves| moves[0] == "1R".parse().unwrap() || moves[0] == "3B".parse().unwrap()));
+
+        // Both engines alike : each game of a pair is the other one with the colors swapped.
+        let players = new_players([&first, &first]);
+        let game = play_match_game(&players, &config, 0);
+        let swapped = play_match_game(&players, &config, 1);
+        assert_eq!(game.moves, swapped.moves);
+        assert_eq!(game.result, 1.0 - swapped.result);
+        assert_eq!(0, game.first_engine_player());
+        assert_eq!(1, swapped.first_engine_player());
+
+        // Once decided, no more game counts.
+        let sprt = MatchConfig { games: 1000, parallel: 1, sprt: Some(Sprt::new(-1000.0, 1000.0)), ..MatchConfig::default() };
+        let stats = run_match(&first, &second, &sprt, &mut |_, _| {});
+        assert!(stats.games() < 1000);
+
+        assert!(matches!(parse_openings("1R\n1R 3R"), Err(DatasetError::Line(2, _))));
+    }
+}
@@ -0,0 +1,423 @@
+#![allow(clippy::needless_return)]
+
+use oware::arena::{read_openings, run_match, EngineConfig, MatchConfig, Sprt, SprtDecision};
+use oware::dataset::{format_result, read_dataset};
+use oware::selfplay::{run_selfplay, SelfPlayConfig};
+use oware::protocol::run_protocol;
+use oware::minmax::{format_line, SearchInfo, DEFAULT_TIME_BUDGET};
+use oware::tune::Tuner;
+use oware::{Clock, DefaultEvaluator, Engine, EvalWeights, Move, SearchConfig, SearchLimits, SearchResult, Searcher, StopHandle};
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "usage: oware [--threads N] [--hash MB] [--mode root-split|lazy-smp] [--ponder on|off]
+             [--movetime MS] [--depth N] [--nodes N] [--clock MS [--inc MS]]
+             [--weights FILE] [--weight NAME=VALUE]...
+       oware tune DATASET [--weights FILE] [--weight NAME=VALUE]... [--passes N] [--out FILE]
+       oware selfplay --out FILE [--games N] [--parallel N] [--depth N] [--nodes N] [--movetime MS]
+             [--random-plies N] [--max-plies N] [--seed N] [--hash MB] [--weights FILE] [--weight NAME=VALUE]...
+       oware match [--games N] [--parallel N] [--openings FILE] [--random-plies N] [--max-plies N] [--seed N]
+             [--sprt ELO0,ELO1 [--alpha A] [--beta B]] [ENGINE OPTION[1|2] VALUE]...
+             where the engine options are --threads, --hash, --mode, --movetime, --depth, --nodes,
+             --clock, --inc, --weights and --weight, for both engines or, suffixed, for one
+       oware protocol";
+
+/// The options of one engine, for the game against the human and for each
+/// engine of a match.
+struct EngineOptions {
+    /// Its limits are kept when no limit is given.
+    engine: EngineConfig,
+    limits: SearchLimits,
+    clock: Option<Duration>,
+    increment: Duration,
+}
+
+impl EngineOptions {
+    fn new(engine: EngineConfig) -> EngineOptions {
+        EngineOptions { engine, limits: SearchLimits::infinite(), clock: None, increment: Duration::ZERO }
+    }
+
+    fn parse(&mut self, arg: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value for {} : {}", arg, value);
+        match arg {
+            "--threads" => self.engine.search.threads = value.parse::<usize>().map_err(|_| invalid())?.max(1),
+            "--hash" => self.engine.search.hash_mb = value.parse().map_err(|_| invalid())?,
+            "--mode" => self.engine.search.mode = value.parse().map_err(|error| format!("{}, {}", invalid(), error))?,
+            "--movetime" | "--depth" | "--nodes" => parse_limit(&mut self.limits, arg, value)?,
+            "--clock" => self.clock = Some(Duration::from_millis(value.parse().map_err(|_| invalid())?)),
+            "--inc" => self.increment = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+            "--weights" | "--weight" => parse_weights(&mut self.engine.weights, arg, value)?,
+            _ => return Err(format!("unknown option {}", arg))
+        }
+        return Ok(());
+    }
+
+    fn engine(self) -> EngineConfig {
+        let limits = SearchLimits { clock: self.clock.map(|remaining| Clock::new(remaining, self.increment)), ..self.limits };
+        if limits == SearchLimits::infinite() {
+            return self.engine;
+        }
+        return EngineConfig { limits, ..self.engine };
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let result = match args.peek().map(String::as_str) {
+        Some("tune") => tune(args.skip(1)),
+        Some("selfplay") => selfplay(args.skip(1)),
+        Some("match") => play_match(args.skip(1)),
+        Some("protocol") => protocol(args.skip(1)),
+        _ => play(args)
+    };
+    if let Err(message) = result {
+        eprintln!("{}\n{}", message, USAGE);
+        process::exit(2);
+    }
+}
+
+/// A game against the human, at the prompt.
+fn play(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut options = EngineOptions::new(EngineConfig {
+        search: SearchConfig::default(),
+        limits: SearchLimits::movetime(DEFAULT_TIME_BUDGET),
+        weights: EvalWeights::default(),
+    });
+    // Search the expected reply while the human thinks.
+    let mut ponder = true;
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("missing value after {}", arg))?;
+        match arg.as_str() {
+            "--ponder" => ponder = match value.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => return Err(format!("invalid value for {} : {}", arg, value))
+            },
+            _ => options.parse(&arg, &value)?
+        }
+    }
+    let config = options.engine();
+
+    let want_to_start = ask_to_start();
+    let mut e = Engine::with_searcher(want_to_start as usize, Searcher::with_evaluator(config.search, DefaultEvaluator::new(config.weights)));
+    e.set_limits(config.limits);
+    run(&mut e, ponder);
+    return Ok(());
+}
+
+fn parse_limit(limits: &mut SearchLimits, arg: &str, value: &str) -> Result<(), String> {
+    let invalid = || format!("invalid value for {} : {}", arg, value);
+    match arg {
+        "--movetime" => limits.movetime = Some(Duration::from_millis(value.parse().map_err(|_| invalid())?)),
+        "--depth" => limits.depth = Some(value.parse().map_err(|_| invalid())?),
+        "--nodes" => limits.nodes = Some(value.parse().map_err(|_| invalid())?),
+        _ => return Err(format!("unknown option {}", arg))
+    }
+    return Ok(());
+}
+
+fn parse_weights(weights: &mut EvalWeights, arg: &str, value: &str) -> Result<(), String> {
+    match arg {
+        "--weights" => *weights = EvalWeights::load(Path::new(value)).map_err(|error| format!("{} : {}", value, error))?,
+        "--weight" => weights.set_from_str(value).map_err(|error| error.to_string())?,
+        _ => return Err(format!("unknown option {}", arg))
+    }
+    return Ok(());
+}
+
+/// Fits the evaluation weights to the results of a dataset, see `Tuner`.
+fn tune(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let dataset = args.next().ok_or("missing dataset file")?;
+    let mut weights = EvalWeights::default();
+    let mut passes = 100;
+    let mut l2 = 0.0;
+    let mut out = None;
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("missing value after {}", arg))?;
+        match arg.as_str() {
+            "--weights" | "--weight" => parse_weights(&mut weights, &arg, &value)?,
+            "--passes" => passes = value.parse().map_err(|_| format!("invalid value for {} : {}", arg, value))?,
+            "--l2" => l2 = value.parse().map_err(|_| format!("invalid value for {} : {}", arg, value))?,
+            "--out" => out = Some(value),
+            _ => return Err(format!("unknown option {}", arg))
+        }
+    }
+
+    let positions = read_dataset(Path::new(&dataset)).map_err(|error| format!("{} : {}", dataset, error))?;
+    let mut tuner = Tuner::new(&positions);
+    if tuner.is_empty() {
+        return Err(format!("{} : no unfinished position to tune on", dataset));
+    }
+    tuner.set_l2(l2);
+    let scale = tuner.fit_scale(&weights);
+    println!("{} positions, scale {:.6}, loss {:.6}", tuner.len(), scale, tuner.loss(&weights));
+
+    let tuned = tuner.tune(&weights, passes, &mut |pass, loss, _| println!("pass {} loss {:.6}", pass, loss));
+    match out {
+        Some(out) => fs::write(&out, tuned.to_string()).map_err(|error| format!("{} : {}", out, error))?,
+        None => print!("{}", tuned)
+    }
+    return Ok(());
+}
+
+/// Plays engine against engine and writes the searched positions to a
+/// dataset, see `run_selfplay`.
+fn selfplay(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut config = SelfPlayConfig::default();
+    let mut limits = SearchLimits::infinite();
+    let mut out = None;
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("missing value after {}", arg))?;
+        let invalid = || format!("invalid value for {} : {}", arg, value);
+        match arg.as_str() {
+            "--out" => out = Some(value),
+            "--games" => config.games = value.parse().map_err(|_| invalid())?,
+            "--parallel" => config.parallel = value.parse().map_err(|_| invalid())?,
+            "--random-plies" => config.random_plies = value.parse().map_err(|_| invalid())?,
+            "--max-plies" => config.max_plies = value.parse().map_err(|_| invalid())?,
+            "--seed" => config.seed = value.parse().map_err(|_| invalid())?,
+            "--hash" => config.hash_mb = value.parse().map_err(|_| invalid())?,
+            "--movetime" | "--depth" | "--nodes" => parse_limit(&mut limits, &arg, &value)?,
+            "--weights" | "--weight" => parse_weights(&mut config.weights, &arg, &value)?,
+            _ => return Err(format!("unknown option {}", arg))
+        }
+    }
+    if limits != SearchLimits::infinite() {
+        config.limits = limits;
+    }
+
+    let out = out.ok_or("missing --out FILE")?;
+    let file = fs::File::create(&out).map_err(|error| format!("{} : {}", out, error))?;
+    let mut writer = io::BufWriter::new(file);
+    let mut finished = 0;
+    let mut write_error = None;
+    run_selfplay(&config, &mut |game| {
+        finished += 1;
+        let result = format_result(game.result);
+        println!("game {} ({}/{}) : {} in {} moves", game.index + 1, finished, config.games, result, game.moves.len());
+
+        let mut lines = format!("# game {} : {}\n", game.index + 1, result);
+        for position in game.positions() {
+            lines.push_str(&format!("{}\n", position));
+        }
+        if write_error.is_none() {
+            write_error = writer.write_all(lines.as_bytes()).err();
+        }
+    });
+    return match write_error {
+        Some(error) => Err(format!("{} : {}", out, error)),
+        None => writer.flush().map_err(|error| format!("{} : {}", out, error))
+    };
+}
+
+/// Plays two configurations of the engine against each other, see
+/// `run_match`.
+fn play_match(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut config = MatchConfig::default();
+    let mut engines = [(); 2].map(|_| EngineOptions::new(EngineConfig::default()));
+    let (mut alpha, mut beta) = (None, None);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("missing value after {}", arg))?;
+        let invalid = || format!("invalid value for {} : {}", arg, value);
+        match arg.as_str() {
+            "--games" => config.games = value.parse().map_err(|_| invalid())?,
+            "--parallel" => config.parallel = value.parse().map_err(|_| invalid())?,
+            "--openings" => config.openings = read_openings(Path::new(&value)).map_err(|error| format!("{} : {}", value, error))?,
+            "--random-plies" => config.random_plies = value.parse().map_err(|_| invalid())?,
+            "--max-plies" => config.max_plies = value.parse().map_err(|_| invalid())?,
+            "--seed" => config.seed = value.parse().map_err(|_| invalid())?,
+            "--sprt" => config.sprt = match value.split_once(',').map(|(elo0, elo1)| (elo0.trim().parse(), elo1.trim().parse())) {
+                Some((Ok(elo0), Ok(elo1))) => Some(Sprt::new(elo0, elo1)),
+                _ => return Err(invalid())
+            },
+            "--alpha" => alpha = Some(value.parse().map_err(|_| invalid())?),
+            "--beta" => beta = Some(value.parse().map_err(|_| invalid())?),
+            _ => {
+                // `--depth2 6` only sets the depth of the second engine.
+                let (option, sides) = match arg.strip_suffix('1') {
+                    Some(option) => (option, 0..1),
+                    None => match arg.strip_suffix('2') {
+                        Some(option) => (option, 1..2),
+                        None => (arg.as_str(), 0..2)
+                    }
+                };
+                for side in sides {
+                    engines[side].parse(option, &value)?;
+                }
+            }
+        }
+    }
+    if let Some(sprt) = &mut config.sprt {
+        sprt.alpha = alpha.unwrap_or(sprt.alpha);
+        sprt.beta = beta.unwrap_or(sprt.beta);
+    }
+    let engines = engines.map(EngineOptions::engine);
+
+    let stats = run_match(&engines[0], &engines[1], &config, &mut |game, stats| {
+        let result = if game.first_engine_player() == 0 { game.result } else { 1.0 - game.result };
+        print!(
+            "game {} : engine 1 as player {}, {} in {} moves, +{} ={} -{}, elo {:+.1} +/- {:.1}",
+            game.index + 1,
+            game.first_engine_player() + 1,
+            format_result(result),
+            game.moves.len(),
+            stats.wins,
+            stats.draws,
+            stats.losses,
+            stats.elo(),
+            stats.elo_error()
+        );
+        match &config.sprt {
+            Some(sprt) => println!(", llr {:.2} in [{:.2}, {:.2}]", stats.llr(sprt), sprt.bounds().0, sprt.bounds().1),
+            None => println!()
+        }
+    });
+
+    println!();
+    println!("Engine 1 against engine 2 : {} games, +{} ={} -{}, score {:.1}%", stats.games(), stats.wins, stats.draws, stats.losses, 100.0 * stats.score());
+    println!("Elo difference : {:+.1} +/- {:.1}", stats.elo(), stats.elo_error());
+    if let Some(sprt) = &config.sprt {
+        let decision = match stats.sprt(sprt) {
+            SprtDecision::Continue => "no decision",
+            SprtDecision::AcceptH0 => "H0 accepted",
+            SprtDecision::AcceptH1 => "H1 accepted"
+        };
+        println!("SPRT elo0 {} elo1 {} : llr {:.2}, {}", sprt.elo0, sprt.elo1, stats.llr(sprt), decision);
+    }
+    return Ok(());
+}
+
+/// Talks the text protocol over stdin/stdout, see `oware::protocol`.
+fn protocol(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    if let Some(arg) = args.next() {
+        return Err(format!("unknown option {}", arg));
+    }
+    return run_protocol(io::stdin().lock(), io::stdout()).map_err(|error| error.to_string());
+}
+
+fn run(engine: &mut Engine, ponder: bool) {
+    let mut turn_number = 0;
+    // The human move the AI expects, second move of its best line.
+    let mut expected_reply = None;
+
+    loop {
+        turn_number += 1;
+        println!();
+
+        println!("PLAYER {} TURN {}", engine.actual_player() + 1, turn_number);
+        println!("{}", engine.board());
+
+        let mv = if engine.is_ai_turn() {
+            println!("Starting turn...");
+            let result = engine.think_with_observer(&mut print_search_info);
+            print_search_result(&result, engine.legal_moves().len());
+            let mv = result.best_move.expect("play reports the end of the game");
+            println!("IA PLAY {}", mv);
+            expected_reply = result.pv.get(1).copied();
+
+            let mut limits = *engine.limits();
+            if let Some(clock) = &mut limits.clock {
+                clock.spend(result.elapsed);
+                println!("Clock : {:.1}s left", clock.remaining.as_secs_f32());
+                engine.set_limits(limits);
+            }
+            mv
+        }
+        else {
+            match expected_reply.take() {
+                Some(expected) if ponder => ask_choice_pondering(engine, expected),
+                _ => ask_choice(engine)
+            }
+        };
+
+        if let Some(winner) = engine.play(mv).expect("moves are checked before being played") {
+            println!("{}", engine.board());
+            display_winner(winner);
+            break;
+        }
+
+        println!();
+    }
+}
+
+fn print_search_info(info: &SearchInfo) {
+    println!(
+        "  depth {} score {} nodes {} nps {} line {}",
+        info.depth,
+        info.score,
+        info.nodes,
+        info.nps,
+        format_line(info.pv)
+    );
+}
+
+fn print_search_result(result: &SearchResult, moves_amount: usize) {
+    println!("Depth : {}, for {} move(s) available.", result.depth, moves_amount);
+    println!(
+        "{} minimax calls, with {} alphabeta cuts.\nElapsed time : {}s",
+        result.nodes,
+        result.cuts,
+        result.elapsed.as_secs_f32()
+    );
+    println!("Score : {}, best line : {}", result.score, format_line(&result.pv));
+}
+
+fn display_winner(winner: usize) {
+    println!();
+    if winner == 2 {
+        println!("It's a draw !");
+    }
+    else if winner == 0 || winner == 1 {
+        println!("Player {} won the game !", winner + 1);
+    }
+}
+
+fn ask_choice(engine: &Engine) -> Move {
+    loop {
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer).expect("Couldn't read the input.");
+        if buffer.trim().is_empty() {
+            continue;
+        }
+
+        match buffer.parse::<Move>() {
+            Ok(mv) if engine.is_move_possible(mv) => return mv,
+            _ => println!("Coup invalide !")
+        }
+    }
+}
+
+/// Asks the human move while the AI searches its answer to `expected`.
+fn ask_choice_pondering(engine: &Engine, expected: Move) -> Move {
+    let stop = StopHandle::new();
+    let (mv, pondering) = crossbeam::scope(|scope| {
+        let pondering = scope.spawn(|_| engine.ponder(expected, &stop));
+        let mv = ask_choice(engine);
+        stop.stop();
+        (mv, pondering.join().unwrap())
+    }).unwrap();
+
+    if let Some(result) = pondering.filter(|_| mv == expected) {
+        println!("Ponder hit, depth {} searched meanwhile.", result.depth);
+    }
+    return mv;
+}
+
+fn ask_to_start() -> bool {
+    println!("Are you the first player ? (true/false)");
+
+    loop {
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice).expect("Couldn't read the input");
+        let choice: bool = match choice.trim().parse() {
+            Ok(v) => v,
+            Err(_) => continue
+        };
+        return choice;
+    }
+}
@@ -0,0 +1,87 @@
+#![allow(clippy::needless_return)]
+
+use oware::dataset::format_result;
+use oware::referee::{referee_game, EngineProcess, RefereeConfig};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "usage: referee [--movetime MS] [--margin MS] [--max-plies N] [--games N] [--out FILE] ENGINE1 ENGINE2
+       where the engines are commands speaking the text protocol, e.g. \"oware protocol\"";
+
+fn main() {
+    if let Err(message) = run(env::args().skip(1)) {
+        eprintln!("{}\n{}", message, USAGE);
+        process::exit(2);
+    }
+}
+
+/// Plays the engines against each other, each moving first in turn.
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut config = RefereeConfig::default();
+    let mut games = 1;
+    let mut out = None;
+    let mut commands = Vec::new();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            commands.push(arg);
+            continue;
+        }
+        let value = args.next().ok_or(format!("missing value after {}", arg))?;
+        let invalid = || format!("invalid value for {} : {}", arg, value);
+        match arg.as_str() {
+            "--movetime" => config.move_time = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+            "--margin" => config.margin = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+            "--max-plies" => config.max_plies = value.parse().map_err(|_| invalid())?,
+            "--games" => games = value.parse().map_err(|_| invalid())?,
+            "--out" => out = Some(value),
+            _ => return Err(format!("unknown option {}", arg))
+        }
+    }
+    if commands.len() != 2 {
+        return Err("expected two engine commands".to_string());
+    }
+
+    let mut engines = Vec::new();
+    for command in &commands {
+        engines.push(EngineProcess::spawn(command).map_err(|error| format!("{} : {}", command, error))?);
+    }
+    let mut record = match &out {
+        Some(out) => Some(fs::File::create(out).map_err(|error| format!("{} : {}", out, error))?),
+        None => None
+    };
+
+    // Points of each engine.
+    let mut points = [0.0; 2];
+    for index in 0..games {
+        let first = index % 2;
+        let (first_engine, second_engine) = match engines.split_at_mut(1) {
+            (one, two) if first == 0 => (&mut one[0], &mut two[0]),
+            (one, two) => (&mut two[0], &mut one[0])
+        };
+        let game = referee_game([first_engine, second_engine], &config);
+
+        points[first] += game.result;
+        points[(first + 1) % 2] += 1.0 - game.result;
+        println!(
+            "game {} : {} against {}, {} in {} moves ({})",
+            index + 1,
+            commands[first],
+            commands[(first + 1) % 2],
+            format_result(game.result),
+            game.moves.len(),
+            game.termination
+        );
+
+        let lines = format!("# game {} : {} against {}\n{}", index + 1, commands[first], commands[(first + 1) % 2], game);
+        match &mut record {
+            Some(file) => file.write_all(lines.as_bytes()).map_err(|error| format!("{} : {}", out.as_ref().unwrap(), error))?,
+            None => print!("{}", lines)
+        }
+    }
+
+    println!("{} : {} points, {} : {} points", commands[0], points[0], commands[1], points[1]);
+    return Ok(());
+}
@@ -1,11 +1,23 @@
+use crate::zobrist;
 use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 
+/// Upper bound of the legal moves of a player: 8 holes, 2 colors each.
+pub const MAX_MOVES: usize = 16;
+
+/// The fields can be edited by hand to set up a position, `refresh_hash`
+/// must then be called to keep `hash` in sync.
+#[derive(PartialEq, Eq, Debug)]
 pub struct Board {
     pub red_holes: [i32; 16],
     pub blue_holes: [i32; 16],
-    pub players_attics: [i32; 2]
+    pub players_attics: [i32; 2],
+    pub side_to_move: usize,
+    hash: u64
 }
 
+#[derive(PartialEq, Eq, Hash, Debug)]
 pub enum Color {
     Red,
     Blue
@@ -19,6 +31,12 @@ impl Clone for Board {
     }
 }
 
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
+}
+
 impl Color {
     pub fn from_integer(id: usize) -> Color {
         match id {
@@ -37,14 +55,339 @@ impl Clone for Color {
     }
 }
 
+/// A move : the seeds of one color taken from one hole (0-based).
+/// Written as the 1-based hole followed by the color, e.g. "5R" or "12B".
+#[derive(PartialEq, Eq, Hash, Debug)]
+pub struct Move {
+    pub hole: usize,
+    pub color: Color
+}
+
+impl Move {
+    pub fn new(hole: usize, color: Color) -> Move {
+        Move { hole, color }
+    }
+}
+
+impl Copy for Move { }
+
+impl Clone for Move {
+    fn clone(&self) -> Move {
+        *self
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ParseMoveError;
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a hole between 1 and 16 followed by R or B, e.g. \"5R\"")
+    }
+}
+
+impl std::error::Error for ParseMoveError { }
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Move, ParseMoveError> {
+        let s = s.trim();
+        let color_start = s.find(|c: char| !c.is_ascii_digit()).ok_or(ParseMoveError)?;
+        let (hole, color) = s.split_at(color_start);
+
+        let hole = match hole.parse::<usize>() {
+            Ok(v) if (1..=16).contains(&v) => v - 1,
+            _ => return Err(ParseMoveError)
+        };
+        let color = match color.trim_start() {
+            "R" | "r" => Color::Red,
+            "B" | "b" => Color::Blue,
+            _ => return Err(ParseMoveError)
+        };
+
+        return Ok(Move::new(hole, color));
+    }
+}
+
+/// Everything `Board::unmake_move` needs to take a move back : the seeds
+/// lifted from the played hole, the red/blue content of each captured hole
+/// (from the last hole backwards) and the seeds added to the attic.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Undo {
+    mv: Move,
+    player: usize,
+    seeds: i32,
+    last_hole: usize,
+    captured: [[u8; 2]; 16],
+    captured_holes: usize,
+    attic_delta: i32,
+    side_to_move: usize,
+    hash: u64
+}
+
+impl Undo {
+    fn new(player: usize, mv: Move) -> Undo {
+        Undo {
+            mv,
+            player,
+            seeds: 0,
+            last_hole: mv.hole,
+            captured: [[0; 2]; 16],
+            captured_holes: 0,
+            attic_delta: 0,
+            side_to_move: 0,
+            hash: 0
+        }
+    }
+
+    pub fn mv(&self) -> Move {
+        return self.mv;
+    }
+
+    /// Seeds captured by the move.
+    pub fn captured(&self) -> i32 {
+        return self.attic_delta;
+    }
+
+    pub fn sown_holes(&self) -> SownHoles {
+        SownHoles {
+            start: self.mv.hole,
+            current: self.mv.hole,
+            step: 1,
+            color: self.mv.color,
+            remaining: self.seeds
+        }
+    }
+
+    pub fn captured_holes(&self) -> CapturedHoles<'_> {
+        CapturedHoles {
+            hole: self.last_hole,
+            captured: self.captured[..self.captured_holes].iter()
+        }
+    }
+}
+
+impl Copy for Undo { }
+
+impl Clone for Undo {
+    fn clone(&self) -> Undo {
+        *self
+    }
+}
+
+/// Holes receiving a seed, in sowing order. A hole appears twice when
+/// the seeds go all the way around the board.
+pub struct SownHoles {
+    start: usize,
+    current: usize,
+    step: usize,
+    color: Color,
+    remaining: i32
+}
+
+impl Iterator for SownHoles {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining <= 0 {
+            return None;
+        }
+
+        self.current = (self.current + self.step) % 16;
+        if self.current == self.start {
+            self.current = (self.current + self.step) % 16;
+        }
+        if self.color == Color::Blue {
+            self.step = 2;
+        }
+        self.remaining -= 1;
+        return Some(self.current);
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining.max(0) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for SownHoles { }
+
+/// A hole emptied by a capture, with what it held just before.
+#[derive(PartialEq, Eq, Debug)]
+pub struct CapturedHole {
+    pub hole: usize,
+    pub red: i32,
+    pub blue: i32
+}
+
+/// Captured holes, from the last sown hole backwards.
+pub struct CapturedHoles<'a> {
+    hole: usize,
+    captured: std::slice::Iter<'a, [u8; 2]>
+}
+
+impl Iterator for CapturedHoles<'_> {
+    type Item = CapturedHole;
+
+    fn next(&mut self) -> Option<CapturedHole> {
+        let captured = self.captured.next()?;
+        let hole = self.hole;
+        self.hole = self.hole.wrapping_sub(1) % 16;
+        return Some(CapturedHole { hole, red: captured[0] as i32, blue: captured[1] as i32 });
+    }
+}
+
+/// What a move did to the board.
+#[derive(PartialEq, Eq, Debug)]
+pub struct MoveOutcome {
+    pub mv: Move,
+    /// Hole which received the last seed.
+    pub last_hole: usize,
+    /// Seeds captured, all of them going to the player's attic.
+    pub captured: i32,
+    /// The game is decided : by the attics, by the seeds left or by a famine
+    /// of the opponent who has to move next.
+    pub game_over: bool,
+    undo: Undo
+}
+
+impl MoveOutcome {
+    pub fn sown_holes(&self) -> SownHoles {
+        return self.undo.sown_holes();
+    }
+
+    pub fn captured_holes(&self) -> CapturedHoles<'_> {
+        return self.undo.captured_holes();
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum MoveError {
+    /// The hole index is not in 0..16.
+    HoleOutOfRange(usize),
+    /// The hole belongs to the other player.
+    WrongOwner { player: usize, hole: usize },
+    /// There is no seed of the requested color in the hole.
+    EmptyHole(Move),
+    /// The game is already decided by the attics or by the seeds left.
+    GameOver
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::HoleOutOfRange(hole) => write!(f, "hole {} does not exist", hole + 1),
+            MoveError::WrongOwner { player, hole } => write!(f, "hole {} does not belong to player {}", hole + 1, player + 1),
+            MoveError::EmptyHole(mv) => write!(f, "no {} seed to play in hole {}", mv.color, mv.hole + 1),
+            MoveError::GameOver => write!(f, "the game is already over")
+        }
+    }
+}
+
+impl std::error::Error for MoveError { }
+
+/// Fixed-capacity list of moves, filled by `Board::legal_moves` without allocating.
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [Move::new(0, Color::Red); MAX_MOVES],
+            len: 0
+        }
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl Copy for MoveList { }
+
+impl Clone for MoveList {
+    fn clone(&self) -> MoveList {
+        *self
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 
 impl Board {
     pub fn new() -> Board {
-        Board {
+        let mut board = Board {
             red_holes: [2; 16],
             blue_holes: [2; 16],
-            players_attics: [0; 2]
+            players_attics: [0; 2],
+            side_to_move: 0,
+            hash: 0
+        };
+        board.refresh_hash();
+        return board;
+    }
+
+    /// Zobrist key of the position, kept up to date by the moves.
+    pub fn hash(&self) -> u64 {
+        return self.hash;
+    }
+
+    /// Zobrist key of the position computed from scratch.
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = zobrist::side_to_move(self.side_to_move);
+        for hole in 0..16 {
+            hash ^= zobrist::red_hole(hole, self.red_holes[hole]);
+            hash ^= zobrist::blue_hole(hole, self.blue_holes[hole]);
+        }
+        for player in 0..2 {
+            hash ^= zobrist::player_attic(player, self.players_attics[player]);
         }
+        return hash;
+    }
+
+    pub fn refresh_hash(&mut self) {
+        self.hash = self.compute_hash();
+    }
+
+    fn set_red_seeds(&mut self, hole: usize, seeds: i32) {
+        self.hash ^= zobrist::red_hole(hole, self.red_holes[hole]) ^ zobrist::red_hole(hole, seeds);
+        self.red_holes[hole] = seeds;
+    }
+
+    fn set_blue_seeds(&mut self, hole: usize, seeds: i32) {
+        self.hash ^= zobrist::blue_hole(hole, self.blue_holes[hole]) ^ zobrist::blue_hole(hole, seeds);
+        self.blue_holes[hole] = seeds;
     }
 
     pub fn get_player_attic(&self, player: usize) -> i32 {
@@ -53,19 +396,20 @@ impl Board {
 
     fn distribute_red_seeds(&mut self, hole: usize) -> usize {
         let mut nb_seeds = self.red_holes[hole];
-        self.red_holes[hole] = 0;
+        self.set_red_seeds(hole, 0);
 
         let mut current_hole = (hole + 1) % 16;
 
         if nb_seeds > 0 {
-            self.red_holes[current_hole] += 1;
+            self.set_red_seeds(current_hole, self.red_holes[current_hole] + 1);
             nb_seeds -= 1;
         }
 
+        // The starting hole is skipped when the seeds go all the way around.
         while nb_seeds > 0 {
+            current_hole = (current_hole + 1) % 16;
             if current_hole != hole {
-                current_hole = (current_hole + 1) % 16;
-                self.red_holes[current_hole] += 1;
+                self.set_red_seeds(current_hole, self.red_holes[current_hole] + 1);
                 nb_seeds -= 1;
             }
         }
@@ -75,18 +419,18 @@ impl Board {
 
     fn distribute_blue_seeds(&mut self, hole: usize) -> usize {
         let mut nb_seeds = self.blue_holes[hole];
-        self.blue_holes[hole] = 0;
+        self.set_blue_seeds(hole, 0);
 
         let mut current_hole = (hole + 1) % 16;
         if nb_seeds > 0 {
-            self.blue_holes[current_hole] += 1;
+            self.set_blue_seeds(current_hole, self.blue_holes[current_hole] + 1);
             nb_seeds -= 1;
         }
 
         while nb_seeds > 0 {
             if current_hole != hole {
                 current_hole = (current_hole + 2) % 16;
-                self.blue_holes[current_hole] += 1;
+                self.set_blue_seeds(current_hole, self.blue_holes[current_hole] + 1);
                 nb_seeds -= 1;
             }
         }
@@ -94,16 +438,18 @@ impl Board {
         return current_hole;
     }
 
-    fn pick_seed(&mut self, last_hole: usize, player: usize) -> i32 {
+    fn pick_seed(&mut self, last_hole: usize, player: usize, undo: &mut Undo) -> i32 {
         let mut current_hole = last_hole;
         let mut nb_blue_seed = self.blue_holes[current_hole];
         let mut nb_red_seed = self.red_holes[current_hole];
 
         let mut nb_total_seed = 0;
         while (nb_red_seed + nb_blue_seed) == 2 || nb_red_seed + nb_blue_seed == 3 {
-            self.red_holes[current_hole] = 0;
-            self.blue_holes[current_hole] = 0;
+            self.set_red_seeds(current_hole, 0);
+            self.set_blue_seeds(current_hole, 0);
 
+            undo.captured[undo.captured_holes] = [nb_red_seed as u8, nb_blue_seed as u8];
+            undo.captured_holes += 1;
             nb_total_seed += nb_red_seed + nb_blue_seed;
 
             current_hole = current_hole.wrapping_sub(1) % 16;
@@ -111,16 +457,101 @@ impl Board {
             nb_red_seed = self.red_holes[current_hole];
         }
 
-        self.players_attics[player] += nb_total_seed;
+        let attic = self.players_attics[player];
+        self.hash ^= zobrist::player_attic(player, attic) ^ zobrist::player_attic(player, attic + nb_total_seed);
+        self.players_attics[player] = attic + nb_total_seed;
+        undo.attic_delta += nb_total_seed;
         return nb_total_seed;
     }
 
-    pub fn play_move(&mut self, player: usize, hole: usize, color: Color) {
-        let last_hole = match color {
-            Color::Red => self.distribute_red_seeds(hole),
-            Color::Blue => self.distribute_blue_seeds(hole)
+    /// Plays `mv` like `play_move_unchecked` and returns what is needed to take it back.
+    pub fn make_move(&mut self, player: usize, mv: Move) -> Undo {
+        let mut undo = Undo::new(player, mv);
+        undo.side_to_move = self.side_to_move;
+        undo.hash = self.hash;
+        undo.seeds = match mv.color {
+            Color::Red => self.red_holes[mv.hole],
+            Color::Blue => self.blue_holes[mv.hole]
+        };
+        undo.last_hole = match mv.color {
+            Color::Red => self.distribute_red_seeds(mv.hole),
+            Color::Blue => self.distribute_blue_seeds(mv.hole)
+        };
+        self.pick_seed(undo.last_hole, player, &mut undo);
+
+        let next_player = (player + 1) % 2;
+        self.hash ^= zobrist::side_to_move(self.side_to_move) ^ zobrist::side_to_move(next_player);
+        self.side_to_move = next_player;
+        return undo;
+    }
+
+    /// Restores the board as it was before the `make_move` that returned `undo`.
+    /// Moves must be taken back in the reverse order they were made.
+    pub fn unmake_move(&mut self, undo: &Undo) {
+        let mut current_hole = undo.last_hole;
+        for captured in &undo.captured[..undo.captured_holes] {
+            self.red_holes[current_hole] = captured[0] as i32;
+            self.blue_holes[current_hole] = captured[1] as i32;
+            current_hole = current_hole.wrapping_sub(1) % 16;
+        }
+        self.players_attics[undo.player] -= undo.attic_delta;
+
+        for hole in undo.sown_holes() {
+            match undo.mv.color {
+                Color::Red => self.red_holes[hole] -= 1,
+                Color::Blue => self.blue_holes[hole] -= 1
+            }
+        }
+        match undo.mv.color {
+            Color::Red => self.red_holes[undo.mv.hole] = undo.seeds,
+            Color::Blue => self.blue_holes[undo.mv.hole] = undo.seeds
+        }
+        self.side_to_move = undo.side_to_move;
+        self.hash = undo.hash;
+    }
+
+    /// Plays a move without checking it, see `play_move_unchecked`.
+    pub fn play_move(&mut self, player: usize, hole: usize, color: Color) -> MoveOutcome {
+        return self.play_move_unchecked(player, Move::new(hole, color));
+    }
+
+    /// Plays `mv` for `player` after checking that it is legal.
+    /// The board is left untouched when an error is returned.
+    pub fn try_play_move(&mut self, player: usize, mv: Move) -> Result<MoveOutcome, MoveError> {
+        if mv.hole >= 16 {
+            return Err(MoveError::HoleOutOfRange(mv.hole));
+        }
+        if mv.hole % 2 != player {
+            return Err(MoveError::WrongOwner { player, hole: mv.hole });
+        }
+        let seeds = match mv.color {
+            Color::Red => self.red_holes[mv.hole],
+            Color::Blue => self.blue_holes[mv.hole]
+        };
+        if seeds <= 0 {
+            return Err(MoveError::EmptyHole(mv));
+        }
+        // A famine only ends the game for the player who has to move.
+        if self.check_win(false) < 3 {
+            return Err(MoveError::GameOver);
+        }
+
+        return Ok(self.play_move_unchecked(player, mv));
+    }
+
+    /// Fast path for the search : `mv` must be one of `legal_moves(player)`,
+    /// anything else leaves the board in an unspecified state.
+    pub fn play_move_unchecked(&mut self, player: usize, mv: Move) -> MoveOutcome {
+        let undo = self.make_move(player, mv);
+        let game_over = self.check_win(false) < 3 || self.check_famine((player + 1) % 2);
+
+        return MoveOutcome {
+            mv,
+            last_hole: undo.last_hole,
+            captured: undo.attic_delta,
+            game_over,
+            undo
         };
-        self.pick_seed(last_hole, player);
     }
 
     pub fn is_this_move_possible(&self, player: usize, hole: usize, color: Color) -> bool {
@@ -143,6 +574,22 @@ impl Board {
             return false;
         }
     }
+
+    /// Every move `player` can play, red ones first, in hole order.
+    pub fn legal_moves(&self, player: usize) -> MoveList {
+        let mut moves = MoveList::new();
+        for hole in (player..16).step_by(2) {
+            if self.red_holes[hole] > 0 {
+                moves.push(Move::new(hole, Color::Red));
+            }
+        }
+        for hole in (player..16).step_by(2) {
+            if self.blue_holes[hole] > 0 {
+                moves.push(Move::new(hole, Color::Blue));
+            }
+        }
+        return moves;
+    }
 }
 
 /* WIN CONDITIONS */
@@ -217,6 +664,85 @@ impl Board {
 
 
 
+/* NOTATION */
+impl Board {
+    /// Parses a position written by `to_notation`.
+    pub fn from_notation(notation: &str) -> Result<Board, NotationError> {
+        let fields: Vec<&str> = notation.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(NotationError::Fields(fields.len()));
+        }
+
+        let holes: Vec<&str> = fields[0].split('/').collect();
+        if holes.len() != 16 {
+            return Err(NotationError::Holes(holes.len()));
+        }
+        let mut board = Board::new();
+        for (hole, seeds) in holes.iter().enumerate() {
+            [board.red_holes[hole], board.blue_holes[hole]] = parse_seed_pair(seeds)?;
+        }
+        board.players_attics = parse_seed_pair(fields[1])?;
+        board.side_to_move = match fields[2] {
+            "1" => 0,
+            "2" => 1,
+            other => return Err(NotationError::SideToMove(other.to_string()))
+        };
+
+        let total = board.red_holes.iter().chain(&board.blue_holes).chain(&board.players_attics).sum::<i32>();
+        if total > 64 {
+            return Err(NotationError::TooManySeeds(total));
+        }
+        board.refresh_hash();
+        return Ok(board);
+    }
+
+    /// The red and blue seeds of the holes 1 to 16, the attics of both
+    /// players and the player to move (1 or 2), e.g.
+    /// `2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2 0,0 1`
+    /// for the start.
+    pub fn to_notation(&self) -> String {
+        let holes: Vec<String> = (0..16).map(|hole| format!("{},{}", self.red_holes[hole], self.blue_holes[hole])).collect();
+        return format!("{} {},{} {}", holes.join("/"), self.players_attics[0], self.players_attics[1], self.side_to_move + 1);
+    }
+}
+
+/// Two seed counts separated by a comma.
+fn parse_seed_pair(text: &str) -> Result<[i32; 2], NotationError> {
+    let error = || NotationError::Seeds(text.to_string());
+    let (first, second) = text.split_once(',').ok_or_else(error)?;
+    let first = first.parse::<u8>().map_err(|_| error())?;
+    let second = second.parse::<u8>().map_err(|_| error())?;
+    return Ok([first as i32, second as i32]);
+}
+
+/// Why a string is not a position, see `Board::from_notation`.
+#[derive(PartialEq, Eq, Debug)]
+pub enum NotationError {
+    /// Amount of space-separated fields instead of 3.
+    Fields(usize),
+    /// Amount of holes instead of 16.
+    Holes(usize),
+    /// Not two seed counts separated by a comma.
+    Seeds(String),
+    SideToMove(String),
+    /// More seeds than the 64 of the game.
+    TooManySeeds(i32)
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::Fields(fields) => write!(f, "expected the holes, the attics and the player to move, found {} field(s)", fields),
+            NotationError::Holes(holes) => write!(f, "expected 16 holes, found {}", holes),
+            NotationError::Seeds(text) => write!(f, "expected red and blue seeds as \"2,2\", found \"{}\"", text),
+            NotationError::SideToMove(text) => write!(f, "expected the player to move, 1 or 2, found \"{}\"", text),
+            NotationError::TooManySeeds(total) => write!(f, "{} seeds, more than the 64 of the game", total)
+        }
+    }
+}
+
+impl std::error::Error for NotationError { }
+
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut ss = String::new();
@@ -257,12 +783,59 @@ impl fmt::Display for Color {
     } 
 }
 
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.hole + 1, self.color)
+    }
+}
+
+
+
 
 
 
+/* RANDOM GAMES */
+/// Small xorshift generator, enough to pick random moves.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // Splitmix64 of the seed, which must not leave the state at 0.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng { state: (z ^ (z >> 31)) | 1 }
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return (self.state % bound as u64) as usize;
+    }
+}
 
+/// `plies` random moves from the start, fewer if the game ends before. The
+/// same seed gives the same moves.
+pub fn random_moves(seed: u64, plies: usize) -> Vec<Move> {
+    let mut rng = Rng::new(seed);
+    let mut board = Board::new();
+    let mut player = 0;
+    let mut moves = Vec::new();
+    while !board.is_final_position() && moves.len() < plies {
+        let legal_moves = board.legal_moves(player);
+        let mv = legal_moves[rng.below(legal_moves.len())];
+        board.make_move(player, mv);
+        moves.push(mv);
+        player = (player + 1) % 2;
+    }
+    return moves;
+}
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -283,7 +856,7 @@ mod tests {
         b1.play_move(1, 2, Color::Red);
         b1.players_attics[0] = 10;
 
-        let mut b2 = *(&mut b1);
+        let mut b2 = b1;
         b2.play_move(0, 3, Color::Red);
 
         assert_eq!(0, b1.red_holes[2]);
@@ -387,7 +960,7 @@ mod tests {
         assert_eq!(1, b.blue_holes[0]);
         assert_eq!(2, b.red_holes[0]);
         assert_eq!(0, final_hole);
-        let pick_return = b.pick_seed(0, 1);
+        let pick_return = b.pick_seed(0, 1, &mut Undo::new(1, Move::new(13, Color::Blue)));
         assert_eq!(0, b.blue_holes[0]);
         assert_eq!(0, b.red_holes[0]);
         assert_eq!(3, pick_return);
@@ -407,7 +980,7 @@ mod tests {
             assert_eq!(3, b.red_holes[i]);
         }
 
-        let pick_return = b.pick_seed(3, 0);
+        let pick_return = b.pick_seed(3, 0, &mut Undo::new(0, Move::new(14, Color::Red)));
         assert_eq!(17, pick_return);
 
         for i in 0..4 {
@@ -454,6 +1027,189 @@ mod tests {
         
     }
 
+    #[test]
+    fn test_try_play_move() {
+        let mut b = Board::new();
+        assert_eq!(Err(MoveError::HoleOutOfRange(16)), b.try_play_move(0, Move::new(16, Color::Red)));
+        assert_eq!(Err(MoveError::WrongOwner { player: 0, hole: 3 }), b.try_play_move(0, Move::new(3, Color::Red)));
+
+        b.blue_holes[4] = 0;
+        assert_eq!(Err(MoveError::EmptyHole(Move::new(4, Color::Blue))), b.try_play_move(0, Move::new(4, Color::Blue)));
+        assert_eq!(2, b.red_holes[4]);
+        assert_eq!(2, b.red_holes[5]);
+
+        let mut expected = b;
+        expected.play_move(1, 13, Color::Blue);
+        let outcome = b.try_play_move(1, Move::new(13, Color::Blue)).unwrap();
+        assert_eq!(0, outcome.last_hole);
+        assert_eq!(0, outcome.captured);
+        assert_eq!(expected.red_holes, b.red_holes);
+        assert_eq!(expected.blue_holes, b.blue_holes);
+
+        b.players_attics[0] = 33;
+        assert_eq!(Err(MoveError::GameOver), b.try_play_move(0, Move::new(0, Color::Red)));
+    }
+
+    #[test]
+    fn test_distribute_full_turn() {
+        let mut b = Board::new();
+        b.red_holes[3] = 17;
+        let last_hole = b.distribute_red_seeds(3);
+
+        assert_eq!(0, b.red_holes[3]);
+        assert_eq!(5, last_hole);
+        for i in 0..16 {
+            if i == 4 || i == 5 {
+                assert_eq!(4, b.red_holes[i]);
+            }
+            else if i != 3 {
+                assert_eq!(3, b.red_holes[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_unmake_move() {
+        let mut b = Board::new();
+        b.red_holes[14] = 5;
+        b.blue_holes[15] = 0;
+        for i in 0..4 {
+            b.blue_holes[i] = 0;
+        }
+        let before = b;
+
+        let undo = b.make_move(0, Move::new(14, Color::Red));
+        assert_eq!(17, b.players_attics[0]);
+        b.unmake_move(&undo);
+        assert_eq!(before, b);
+
+        b.red_holes[3] = 17;
+        let before = b;
+        let undo = b.make_move(1, Move::new(3, Color::Red));
+        b.unmake_move(&undo);
+        assert_eq!(before, b);
+    }
+
+    #[test]
+    fn test_make_unmake_random_games() {
+        for game in 0..500 {
+            let mut b = Board::new();
+            let mut history = Vec::new();
+
+            for (ply, mv) in random_moves(game, 200).into_iter().enumerate() {
+                let player = ply % 2;
+                let before = b;
+                let undo = b.make_move(player, mv);
+                let mut played = before;
+                played.play_move_unchecked(player, mv);
+                assert_eq!(played, b);
+
+                b.unmake_move(&undo);
+                assert_eq!(before, b);
+
+                history.push((before, b.make_move(player, mv)));
+            }
+
+            while let Some((before, undo)) = history.pop() {
+                b.unmake_move(&undo);
+                assert_eq!(before, b);
+            }
+            assert_eq!(Board::new(), b);
+        }
+    }
+
+    #[test]
+    fn test_move_outcome() {
+        let mut b = Board::new();
+        b.blue_holes[0] = 0;
+        b.blue_holes[1] = 0;
+        b.blue_holes[2] = 0;
+        b.blue_holes[3] = 0;
+        b.red_holes[14] = 5;
+        b.blue_holes[15] = 0;
+
+        let outcome = b.play_move(0, 14, Color::Red);
+        assert_eq!(Move::new(14, Color::Red), outcome.mv);
+        assert_eq!(3, outcome.last_hole);
+        assert_eq!(17, outcome.captured);
+        assert_eq!(false, outcome.game_over);
+        assert_eq!(vec![15, 0, 1, 2, 3], outcome.sown_holes().collect::<Vec<_>>());
+
+        let captured: Vec<CapturedHole> = outcome.captured_holes().collect();
+        assert_eq!(6, captured.len());
+        assert_eq!(CapturedHole { hole: 3, red: 3, blue: 0 }, captured[0]);
+        assert_eq!(CapturedHole { hole: 0, red: 3, blue: 0 }, captured[3]);
+        assert_eq!(CapturedHole { hole: 15, red: 3, blue: 0 }, captured[4]);
+        assert_eq!(CapturedHole { hole: 14, red: 0, blue: 2 }, captured[5]);
+        assert_eq!(outcome.captured, captured.iter().map(|c| c.red + c.blue).sum::<i32>());
+
+        let outcome = b.play_move(1, 13, Color::Blue);
+        assert_eq!(vec![14, 0], outcome.sown_holes().collect::<Vec<_>>());
+        assert_eq!(0, outcome.captured_holes().count());
+
+        b.red_holes[3] = 17;
+        let outcome = b.play_move(1, 3, Color::Red);
+        let sown: Vec<usize> = outcome.sown_holes().collect();
+        assert_eq!(17, sown.len());
+        assert_eq!(false, sown.contains(&3));
+        assert_eq!(outcome.last_hole, sown[16]);
+
+        let mut b = Board::new();
+        b.players_attics[0] = 30;
+        b.blue_holes[3] = 0;
+        let outcome = b.play_move(0, 0, Color::Blue);
+        assert_eq!(true, outcome.game_over);
+    }
+
+    #[test]
+    fn test_hash() {
+        let b = Board::new();
+        assert_eq!(b.compute_hash(), b.hash());
+
+        let mut b1 = Board::new();
+        b1.play_move(0, 0, Color::Red);
+        b1.play_move(1, 9, Color::Blue);
+        b1.play_move(0, 4, Color::Red);
+
+        let mut b2 = Board::new();
+        b2.play_move(0, 4, Color::Red);
+        b2.play_move(1, 9, Color::Blue);
+        b2.play_move(0, 0, Color::Red);
+
+        assert_eq!(b1, b2);
+        assert_eq!(b1.hash(), b2.hash());
+        assert_eq!(1, b1.side_to_move);
+
+        b2.side_to_move = 0;
+        b2.refresh_hash();
+        assert_ne!(b1.hash(), b2.hash());
+
+        b1.red_holes[7] += 1;
+        assert_ne!(b1.compute_hash(), b1.hash());
+        b1.refresh_hash();
+        assert_eq!(b1.compute_hash(), b1.hash());
+    }
+
+    #[test]
+    fn test_hash_random_games() {
+        for game in 0..500 {
+            let mut b = Board::new();
+            for (ply, mv) in random_moves(game, 200).into_iter().enumerate() {
+                let player = ply % 2;
+                let before = b;
+                let undo = b.make_move(player, mv);
+                assert_eq!(b.compute_hash(), b.hash());
+                assert_ne!(before.hash(), b.hash());
+
+                b.unmake_move(&undo);
+                assert_eq!(before.hash(), b.hash());
+
+                b.play_move_unchecked(player, mv);
+                assert_eq!(b.compute_hash(), b.hash());
+            }
+        }
+    }
+
     #[test]
     fn test_is_this_move_possible() {
         let mut b = Board::new();
@@ -481,6 +1237,86 @@ mod tests {
         assert_eq!(false, b.is_this_move_possible(0, 1, Color::Blue));
     }
 
+    #[test]
+    fn test_legal_moves() {
+        let mut b = Board::new();
+        let moves = b.legal_moves(0);
+        assert_eq!(16, moves.len());
+        assert_eq!(Move::new(0, Color::Red), moves[0]);
+        assert_eq!(Move::new(14, Color::Blue), moves[15]);
+
+        b.play_move(0, 0, Color::Red);
+        b.blue_holes[3] = 0;
+        let moves = b.legal_moves(1);
+        assert_eq!(15, moves.len());
+        for mv in &moves {
+            assert_eq!(1, mv.hole % 2);
+            assert_eq!(true, b.is_this_move_possible(1, mv.hole, mv.color));
+        }
+        assert_eq!(false, moves.contains(&Move::new(3, Color::Blue)));
+
+        for player in 0..2 {
+            let moves = b.legal_moves(player);
+            let mut expected = 0;
+            for color in 0..2 {
+                for hole in 0..16 {
+                    if b.is_this_move_possible(player, hole, Color::from_integer(color)) {
+                        assert_eq!(Move::new(hole, Color::from_integer(color)), moves[expected]);
+                        expected += 1;
+                    }
+                }
+            }
+            assert_eq!(expected, moves.len());
+        }
+    }
+
+    #[test]
+    fn test_move_notation() {
+        assert_eq!(Ok(Move::new(4, Color::Red)), "5R".parse::<Move>());
+        assert_eq!(Ok(Move::new(11, Color::Blue)), "12B".parse::<Move>());
+        assert_eq!(Ok(Move::new(15, Color::Blue)), " 16 b\n".parse::<Move>());
+
+        assert_eq!(Err(ParseMoveError), "0R".parse::<Move>());
+        assert_eq!(Err(ParseMoveError), "17B".parse::<Move>());
+        assert_eq!(Err(ParseMoveError), "5".parse::<Move>());
+        assert_eq!(Err(ParseMoveError), "R".parse::<Move>());
+        assert_eq!(Err(ParseMoveError), "5G".parse::<Move>());
+
+        assert_eq!("5R", Move::new(4, Color::Red).to_string());
+        assert_eq!("12B", Move::new(11, Color::Blue).to_string());
+    }
+
+    #[test]
+    fn test_board_notation() {
+        let start = "2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2/2,2 0,0 1";
+        assert_eq!(start, Board::new().to_notation());
+        assert_eq!(Ok(Board::new()), Board::from_notation(start));
+
+        let mut b = Board::new();
+        for mv in ["1R", "14B", "11B", "8B", "5B", "4R"] {
+            b.try_play_move(b.side_to_move, mv.parse().unwrap()).unwrap();
+        }
+        let notation = b.to_notation();
+        let parsed = Board::from_notation(&notation).unwrap();
+        assert_eq!(b, parsed);
+        assert_eq!(b.hash(), parsed.hash());
+        assert_eq!(notation, parsed.to_notation());
+
+        let b = Board::from_notation("0,0/1,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/0,0/3,5 30,25 2").unwrap();
+        assert_eq!(1, b.side_to_move);
+        assert_eq!([30, 25], b.players_attics);
+        assert_eq!(3, b.red_holes[15]);
+        assert_eq!(5, b.blue_holes[15]);
+        assert_eq!(b.compute_hash(), b.hash());
+
+        assert_eq!(Err(NotationError::Fields(2)), Board::from_notation("2,2/2,2 0,0"));
+        assert_eq!(Err(NotationError::Holes(2)), Board::from_notation("2,2/2,2 0,0 1"));
+        assert_eq!(Err(NotationError::Seeds("2;2".to_string())), Board::from_notation(&start.replacen("2,2", "2;2", 1)));
+        assert_eq!(Err(NotationError::Seeds("-1,0".to_string())), Board::from_notation(&start.replace("0,0", "-1,0")));
+        assert_eq!(Err(NotationError::SideToMove("3".to_string())), Board::from_notation(&start.replace(" 1", " 3")));
+        assert_eq!(Err(NotationError::TooManySeeds(65)), Board::from_notation(&start.replace("0,0", "1,0")));
+    }
+
     #[test]
     fn test_check_famine() {
         let mut b = Board::new();
@@ -0,0 +1,165 @@
+use crate::board::{Board, Move, MoveError};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A position with the result of the game it comes from, one line of a
+/// dataset file : `1-0 | 1R 14B 11B`, or `1-0 | 1R 14B 11B | 52` with the
+/// score of a search. The result is `1-0` when the first player won, `0-1`
+/// when the second did and `1/2` for a draw ; the position is the one
+/// reached by the moves from the start. Lines starting with `#` are comments.
+#[derive(PartialEq, Debug)]
+pub struct LabeledPosition {
+    pub moves: Vec<Move>,
+    /// Points of the first player : 1, 0.5 or 0.
+    pub result: f64,
+    /// Score of the search from the position, seen by the player to move.
+    pub score: Option<i32>,
+}
+
+impl LabeledPosition {
+    /// The position and the player to move, the moves being checked.
+    pub fn board(&self) -> Result<(Board, usize), MoveError> {
+        let mut board = Board::new();
+        let mut player = 0;
+        for &mv in &self.moves {
+            board.try_play_move(player, mv)?;
+            player = (player + 1) % 2;
+        }
+        return Ok((board, player));
+    }
+
+    /// Points of `player`.
+    pub fn result_for(&self, player: usize) -> f64 {
+        return if player == 0 { self.result } else { 1.0 - self.result };
+    }
+
+    pub fn parse(line: &str) -> Result<LabeledPosition, DatasetError> {
+        let mut fields = line.split('|');
+        let (result, moves) = match (fields.next(), fields.next()) {
+            (Some(result), Some(moves)) => (result, moves),
+            _ => return Err(DatasetError::Syntax(line.to_string())),
+        };
+        let score = match fields.next().map(str::trim) {
+            Some(score) => Some(score.parse::<i32>().map_err(|_| DatasetError::Syntax(score.to_string()))?),
+            None => None,
+        };
+        if let Some(extra) = fields.next() {
+            return Err(DatasetError::Syntax(extra.trim().to_string()));
+        }
+        let result = match result.trim() {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2" => 0.5,
+            other => return Err(DatasetError::Syntax(other.to_string())),
+        };
+        let moves = moves
+            .split_whitespace()
+            .map(|mv| mv.parse::<Move>().map_err(|_| DatasetError::Syntax(mv.to_string())))
+            .collect::<Result<Vec<Move>, DatasetError>>()?;
+
+        let position = LabeledPosition { moves, result, score };
+        position.board().map_err(DatasetError::IllegalMove)?;
+        return Ok(position);
+    }
+}
+
+impl fmt::Display for LabeledPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} |", format_result(self.result))?;
+        for mv in &self.moves {
+            write!(f, " {}", mv)?;
+        }
+        if let Some(score) = self.score {
+            write!(f, " | {}", score)?;
+        }
+        Ok(())
+    }
+}
+
+/// `1-0`, `0-1` or `1/2` for the points of the first player.
+pub fn format_result(result: f64) -> &'static str {
+    if result > 0.75 {
+        return "1-0";
+    }
+    if result < 0.25 {
+        return "0-1";
+    }
+    return "1/2";
+}
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    Syntax(String),
+    IllegalMove(MoveError),
+    /// Error of the line with this number, counted from 1.
+    Line(usize, Box<DatasetError>),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(error) => write!(f, "cannot read the dataset : {}", error),
+            DatasetError::Syntax(text) => write!(f, "unexpected \"{}\", expected e.g. \"1-0 | 1R 14B\"", text),
+            DatasetError::IllegalMove(error) => write!(f, "illegal move : {}", error),
+            DatasetError::Line(line, error) => write!(f, "line {} : {}", line, error)
+        }
+    }
+}
+
+impl std::error::Error for DatasetError { }
+
+impl From<io::Error> for DatasetError {
+    fn from(error: io::Error) -> DatasetError {
+        DatasetError::Io(error)
+    }
+}
+
+pub fn parse_dataset(text: &str) -> Result<Vec<LabeledPosition>, DatasetError> {
+    let mut positions = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        positions.push(LabeledPosition::parse(line).map_err(|error| DatasetError::Line(index + 1, Box::new(error)))?);
+    }
+    return Ok(positions);
+}
+
+pub fn read_dataset(path: &Path) -> Result<Vec<LabeledPosition>, DatasetError> {
+    return parse_dataset(&fs::read_to_string(path)?);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
+
+    #[test]
+    fn test_parse_dataset() {
+        let text = "# first game\n1-0 | 1R 14B 11B\n\n1/2 |\n0-1 | 1R | -12\n";
+        let positions = parse_dataset(text).unwrap();
+        assert_eq!(3, positions.len());
+        assert_eq!(None, positions[0].score);
+        assert_eq!(Some(-12), positions[2].score);
+        assert_eq!(vec![Move::new(0, Color::Red), Move::new(13, Color::Blue), Move::new(10, Color::Blue)], positions[0].moves);
+        assert_eq!(1.0, positions[0].result);
+        assert_eq!(0.5, positions[1].result);
+        assert_eq!(1.0, positions[2].result_for(1));
+        assert_eq!(Ok((Board::new(), 0)), positions[1].board());
+        assert_eq!(1, positions[2].board().unwrap().1);
+
+        for position in &positions {
+            assert_eq!(*position, LabeledPosition::parse(&position.to_string()).unwrap());
+        }
+
+        assert!(matches!(parse_dataset("1-0 | 1R\n2-0 | 1R"), Err(DatasetError::Line(2, _))));
+        assert!(matches!(LabeledPosition::parse("1-0 1R"), Err(DatasetError::Syntax(_))));
+        assert!(matches!(LabeledPosition::parse("1-0 | 1R | x"), Err(DatasetError::Syntax(_))));
+        assert!(matches!(LabeledPosition::parse("1-0 | 1R | 3 | 4"), Err(DatasetError::Syntax(_))));
+        assert!(matches!(LabeledPosition::parse("1-0 | 1R 3R"), Err(DatasetError::IllegalMove(_))));
+    }
+}
@@ -1,121 +1,178 @@
 use crate::board::*;
+use crate::eval::*;
+use crate::limits::*;
 use crate::minmax::*;
-use std::io;
+use std::time::Duration;
 
-pub struct Engine {
+/// A game between the AI and an outside player, without any I/O.
+pub struct Engine<E: Evaluator = DefaultEvaluator> {
     game_board: Board,
     actual_player: usize,
-    ai_player: usize
+    ai_player: usize,
+    searcher: Searcher<E>,
+    limits: SearchLimits
 }
 
 impl Engine {
-    pub fn new() -> Engine {
-        let want_to_start = Engine::ask_to_start();
+    pub fn new(ai_player: usize) -> Engine {
+        return Engine::with_searcher(ai_player, Searcher::default());
+    }
+}
+
+impl<E: Evaluator> Engine<E> {
+    pub fn with_searcher(ai_player: usize, searcher: Searcher<E>) -> Engine<E> {
         return Engine {
             game_board: Board::new(),
             actual_player: 0,
-            ai_player: want_to_start as usize
+            ai_player,
+            searcher,
+            limits: SearchLimits::movetime(DEFAULT_TIME_BUDGET)
         }
     }
 
-    pub fn run(&mut self) {
-        let mut turn_number = 0;
-
-        loop {
-            turn_number += 1;
-            println!("");
-
-            println!("PLAYER {} TURN {}", self.actual_player + 1, turn_number);
-            println!("{}", self.game_board);
-
-            if self.game_board.check_famine(self.actual_player) {
-                Engine::display_winner((self.actual_player + 1) % 2);
-                break;
-            }
-
-            let (color, hole): (Color, usize);
-            
-            if self.actual_player == self.ai_player {
-                let indices = decision_minmax(&self.game_board, self.actual_player);
-                println!("IA PLAY {} {}", indices.1 + 1, indices.0);
-                color = indices.0;
-                hole = indices.1;
-            }
-            else {
-                let (choice_color, choice_hole) = self.ask_choice();
-                color = choice_color;
-                hole = choice_hole;
-            }
-            
-            self.game_board.play_move(self.actual_player, hole, color);
-
-            let winner = self.game_board.check_win(false);
-            if winner < 3 {
-                println!("{}", self.game_board);
-                Engine::display_winner(winner);
-                break;
-            }
-
-            self.update_actual_player();
-            println!("");
+    pub fn board(&self) -> &Board {
+        return &self.game_board;
+    }
+
+    pub fn actual_player(&self) -> usize {
+        return self.actual_player;
+    }
+
+    pub fn ai_player(&self) -> usize {
+        return self.ai_player;
+    }
+
+    pub fn is_ai_turn(&self) -> bool {
+        return self.actual_player == self.ai_player;
+    }
+
+    /// Thinking time of the AI for each move.
+    pub fn set_time_budget(&mut self, time_budget: Duration) {
+        self.limits = SearchLimits::movetime(time_budget);
+    }
+
+    pub fn limits(&self) -> &SearchLimits {
+        return &self.limits;
+    }
+
+    /// Limits of the next searches. A clock is not updated by the engine,
+    /// see `Clock::spend`.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    pub fn is_move_possible(&self, mv: Move) -> bool {
+        return self.game_board.is_this_move_possible(self.actual_player, mv.hole, mv.color);
+    }
+
+    pub fn legal_moves(&self) -> MoveList {
+        return self.game_board.legal_moves(self.actual_player);
+    }
+
+    /// Searches the best move for the player to move.
+    pub fn think(&self) -> SearchResult {
+        return self.searcher.search(&self.game_board, self.actual_player, &self.limits);
+    }
+
+    /// Same as `think`, `observer` being told about every completed depth.
+    pub fn think_with_observer(&self, observer: &mut dyn SearchObserver) -> SearchResult {
+        return self.searcher.search_with_observer(&self.game_board, self.actual_player, &self.limits, observer);
+    }
+
+    /// Searches, until `stop` is stopped or down to the depth of the limits,
+    /// the position the AI expects after `expected` is played by the other
+    /// player. The search fills the transposition table, so on a ponder hit
+    /// the next `think` starts from that work. Returns `None` when there is
+    /// nothing to ponder.
+    pub fn ponder(&self, expected: Move, stop: &StopHandle) -> Option<SearchResult> {
+        return self.ponder_with_observer(expected, stop, &mut ());
+    }
+
+    /// Same as `ponder`, `observer` being told about every completed depth.
+    pub fn ponder_with_observer(&self, expected: Move, stop: &StopHandle, observer: &mut dyn SearchObserver) -> Option<SearchResult> {
+        if self.is_ai_turn() {
+            return None;
         }
+        let mut board = self.game_board;
+        let outcome = board.try_play_move(self.actual_player, expected).ok()?;
+        if outcome.game_over || board.check_famine(self.ai_player) {
+            return None;
+        }
+        let limits = SearchLimits { depth: self.limits.depth, ..SearchLimits::infinite() };
+        return Some(self.searcher.search_with_stop(&board, self.ai_player, &limits, stop, observer));
     }
 
-    fn display_winner(winner: usize) {
-        println!("");
-        if winner == 2 {
-            println!("It's a draw !");
+    /// Plays a move for the player to move and hands the turn over.
+    /// Returns the winner (2 for a draw) when the move ends the game.
+    pub fn play(&mut self, mv: Move) -> Result<Option<usize>, MoveError> {
+        let outcome = self.game_board.try_play_move(self.actual_player, mv)?;
+
+        let winner = self.game_board.check_win(false);
+        if winner < 3 {
+            return Ok(Some(winner));
         }
-        else if winner == 0 || winner == 1 {
-            println!("Player {} won the game !", winner + 1);
+        if outcome.game_over {
+            // The opponent is starved.
+            return Ok(Some(self.actual_player));
         }
+
+        self.update_actual_player();
+        return Ok(None);
     }
 
     fn update_actual_player(&mut self) {
         self.actual_player = (self.actual_player + 1) % 2;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
 
-    fn ask_choice(&self) -> (Color, usize) {
-        loop {
-            let mut buffer = String::new();
-            io::stdin().read_line(&mut buffer).expect("Couldn't read the input.");
-            let vec: Vec<&str> = buffer.split_whitespace().collect();
-    
-            if vec.len() >= 2 {
-                let hole: usize;
-                let color: Color;
-    
-                hole = match vec[0].parse::<usize>() {
-                    Ok(v) => v,
-                    Err(_) => continue
-                };
-                color = match vec[1].parse::<char>() {
-                    Ok(c) => if c == 'R' {Color::Red} else if c == 'B' {Color::Blue} else {continue},
-                    Err(_) => continue
-                };
-                
-                if hole > 0 && self.game_board.is_this_move_possible(self.actual_player, hole - 1, color) {
-                    return (color, hole - 1)
-                }
-                else {
-                    println!("Coup invalide !");
-                    continue;
-                }
-            }
+    #[test]
+    fn test_play_famine() {
+        // This random game ends when the first player starves the second one.
+        let moves = random_moves(58, usize::MAX);
+        assert_eq!(49, moves.len());
+        let mut engine = Engine::new(1);
+        for &mv in &moves[..48] {
+            assert_eq!(None, engine.play(mv).unwrap());
         }
+        assert_eq!(Some(0), engine.play(moves[48]).unwrap());
+        assert!(engine.board().check_famine(1));
     }
-    
-    fn ask_to_start() -> bool {
-        println!("Are you the first player ? (true/false)");
-    
-        loop {
-            let mut choice = String::new();
-            io::stdin().read_line(&mut choice).expect("Couldn't read the input");
-            let choice: bool = match choice.trim().parse() {
-                Ok(v) => v,
-                Err(_) => continue
-            };
-            return choice;
+
+    #[test]
+    fn test_ponder() {
+        let mut engine = Engine::with_searcher(1, Searcher::new(SearchConfig { threads: 1, hash_mb: 1, ..SearchConfig::default() }));
+        let stop = StopHandle::new();
+        let expected = Move::new(2, Color::Red);
+
+        // Nothing to ponder on its own turn or on an illegal move.
+        engine.play(Move::new(0, Color::Red)).unwrap();
+        assert!(engine.ponder(Move::new(3, Color::Blue), &stop).is_none());
+        engine.play(Move::new(1, Color::Blue)).unwrap();
+        assert!(engine.ponder(Move::new(0, Color::Red), &stop).is_none());
+
+        engine.set_limits(SearchLimits::depth(6));
+        let result = engine.ponder(expected, &stop).unwrap();
+        assert_eq!(6, result.depth);
+
+        // After the ponder hit, the table already knows the answers to the
+        // AI's moves : the same search needs far fewer nodes.
+        engine.play(expected).unwrap();
+        assert!(engine.is_ai_turn());
+        let hit = engine.think();
+
+        let mut fresh = Engine::with_searcher(1, Searcher::new(SearchConfig { threads: 1, hash_mb: 1, ..SearchConfig::default() }));
+        fresh.set_limits(SearchLimits::depth(6));
+        for mv in [Move::new(0, Color::Red), Move::new(1, Color::Blue), expected] {
+            fresh.play(mv).unwrap();
         }
+        let cold = fresh.think();
+        assert_eq!((cold.best_move, cold.score), (hit.best_move, hit.score));
+        assert_eq!((result.best_move, result.score), (hit.best_move, hit.score));
+        assert!(hit.nodes * 10 < cold.nodes, "{} nodes after pondering, {} without", hit.nodes, cold.nodes);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,383 @@
+use crate::board::Board;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Scores the positions the search does not finish, seen by `player` : the
+/// higher, the better for `player`. Won, lost and drawn positions are scored
+/// by the search itself. Shared by all the search threads.
+pub trait Evaluator: Sync {
+    fn evaluate(&self, board: &Board, player: usize) -> i32;
+}
+
+/// Amount of terms in `EvalWeights`.
+pub const TERMS: usize = 8;
+
+/// Names of the terms, in the order of `EvalWeights::to_array`.
+pub const TERM_NAMES: [&str; TERMS] = [
+    "contempt",
+    "attic_difference",
+    "own_seeds",
+    "opponent_seeds",
+    "own_blue_seeds",
+    "color_balance",
+    "vulnerable_holes",
+    "mobility",
+];
+
+/// Coefficients of the `DefaultEvaluator`, every term being counted from the
+/// point of view of the evaluated player. The defaults are the original
+/// heuristic of the engine.
+///
+/// The evaluation is antisymmetric, a position being worth the opposite for
+/// the other player, only when the contempt and `own_blue_seeds` are 0 and
+/// `own_seeds` is the opposite of `opponent_seeds`. The defaults are not :
+/// the search keeps apart the positions it scores for each player.
+#[derive(PartialEq, Eq, Debug)]
+pub struct EvalWeights {
+    /// Added to every unfinished position : above 0, the engine prefers to
+    /// play on rather than draw. Whichever player evaluates gets it.
+    pub contempt: i32,
+    /// Seeds in the player's attic minus the opponent's.
+    pub attic_difference: i32,
+    /// Seeds left in the player's holes.
+    pub own_seeds: i32,
+    /// Seeds left in the opponent's holes.
+    pub opponent_seeds: i32,
+    /// Blue seeds left in the player's holes.
+    pub own_blue_seeds: i32,
+    /// Red minus blue seeds left in the player's holes, minus the same count
+    /// in the opponent's.
+    pub color_balance: i32,
+    /// Opponent's holes with one or two seeds, which a sowing ending there
+    /// captures, minus the player's.
+    pub vulnerable_holes: i32,
+    /// Legal moves of the player minus the opponent's. Costs two move
+    /// generations per evaluation unless 0.
+    pub mobility: i32,
+}
+
+impl EvalWeights {
+    pub fn to_array(&self) -> [i32; TERMS] {
+        return [
+            self.contempt,
+            self.attic_difference,
+            self.own_seeds,
+            self.opponent_seeds,
+            self.own_blue_seeds,
+            self.color_balance,
+            self.vulnerable_holes,
+            self.mobility,
+        ];
+    }
+
+    pub fn from_array(values: [i32; TERMS]) -> EvalWeights {
+        let [contempt, attic_difference, own_seeds, opponent_seeds, own_blue_seeds, color_balance, vulnerable_holes, mobility] = values;
+        EvalWeights { contempt, attic_difference, own_seeds, opponent_seeds, own_blue_seeds, color_balance, vulnerable_holes, mobility }
+    }
+
+    pub fn get(&self, name: &str) -> Option<i32> {
+        let index = TERM_NAMES.iter().position(|&term| term == name)?;
+        return Some(self.to_array()[index]);
+    }
+
+    pub fn set(&mut self, name: &str, value: i32) -> Result<(), WeightsError> {
+        let index = TERM_NAMES.iter().position(|&term| term == name).ok_or(WeightsError::UnknownTerm(name.to_string()))?;
+        let mut values = self.to_array();
+        values[index] = value;
+        *self = EvalWeights::from_array(values);
+        return Ok(());
+    }
+
+    /// Sets a term from a `name=value` string, as given on the command line.
+    pub fn set_from_str(&mut self, assignment: &str) -> Result<(), WeightsError> {
+        let (name, value) = assignment.split_once('=').ok_or(WeightsError::Syntax(assignment.to_string()))?;
+        return self.set_term(name.trim(), value.trim());
+    }
+
+    /// Reads the weights from a TOML file of `name = value` lines or from a
+    /// flat JSON object. The terms not given keep their default value.
+    pub fn parse(text: &str) -> Result<EvalWeights, WeightsError> {
+        let mut weights = EvalWeights::default();
+        let text = text.trim();
+        if let Some(object) = text.strip_prefix('{') {
+            let object = object.strip_suffix('}').ok_or(WeightsError::Syntax(text.to_string()))?;
+            for member in object.split(',').filter(|member| !member.trim().is_empty()) {
+                let (name, value) = member.split_once(':').ok_or(WeightsError::Syntax(member.trim().to_string()))?;
+                let name = name.trim();
+                let name = name.strip_prefix('"').and_then(|name| name.strip_suffix('"')).ok_or(WeightsError::Syntax(member.trim().to_string()))?;
+                weights.set_term(name, value.trim())?;
+            }
+            return Ok(weights);
+        }
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            weights.set_from_str(line)?;
+        }
+        return Ok(weights);
+    }
+
+    pub fn load(path: &Path) -> Result<EvalWeights, WeightsError> {
+        return EvalWeights::parse(&fs::read_to_string(path)?);
+    }
+
+    fn set_term(&mut self, name: &str, value: &str) -> Result<(), WeightsError> {
+        let value = value.parse::<i32>().map_err(|_| WeightsError::InvalidValue { term: name.to_string(), value: value.to_string() })?;
+        return self.set(name, value);
+    }
+}
+
+impl Default for EvalWeights {
+    fn default() -> EvalWeights {
+        EvalWeights {
+            contempt: 64,
+            attic_difference: 3,
+            own_seeds: 0,
+            opponent_seeds: -1,
+            own_blue_seeds: 1,
+            color_balance: 0,
+            vulnerable_holes: 0,
+            mobility: 0,
+        }
+    }
+}
+
+impl Copy for EvalWeights {}
+
+impl Clone for EvalWeights {
+    fn clone(&self) -> EvalWeights {
+        *self
+    }
+}
+
+/// The TOML form read by `EvalWeights::parse`.
+impl fmt::Display for EvalWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in TERM_NAMES.iter().zip(self.to_array()) {
+            writeln!(f, "{} = {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum WeightsError {
+    Io(io::Error),
+    /// Neither `name = value` nor `"name": value`.
+    Syntax(String),
+    UnknownTerm(String),
+    InvalidValue { term: String, value: String },
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeightsError::Io(error) => write!(f, "cannot read the weights : {}", error),
+            WeightsError::Syntax(text) => write!(f, "expected name = value, got \"{}\"", text),
+            WeightsError::UnknownTerm(name) => write!(f, "unknown term {}, expected one of {}", name, TERM_NAMES.join(", ")),
+            WeightsError::InvalidValue { term, value } => write!(f, "invalid value for {} : {}", term, value)
+        }
+    }
+}
+
+impl std::error::Error for WeightsError { }
+
+impl From<io::Error> for WeightsError {
+    fn from(error: io::Error) -> WeightsError {
+        WeightsError::Io(error)
+    }
+}
+
+/// Value of every term of `EvalWeights` in a position, seen by `player` :
+/// the evaluation is their sum weighted by the weights.
+pub fn features(board: &Board, player: usize) -> [i32; TERMS] {
+    let opponent = (player + 1) % 2;
+    let mut own_seeds = 0;
+    let mut opponent_seeds = 0;
+    let mut own_blue_seeds = 0;
+    let mut color_balance = 0;
+    let mut vulnerable_holes = 0;
+    for hole in (player..16).step_by(2) {
+        let seeds = board.red_holes[hole] + board.blue_holes[hole];
+        own_seeds += seeds;
+        own_blue_seeds += board.blue_holes[hole];
+        color_balance += board.red_holes[hole] - board.blue_holes[hole];
+        vulnerable_holes -= is_vulnerable(seeds);
+    }
+    for hole in (opponent..16).step_by(2) {
+        let seeds = board.red_holes[hole] + board.blue_holes[hole];
+        opponent_seeds += seeds;
+        color_balance -= board.red_holes[hole] - board.blue_holes[hole];
+        vulnerable_holes += is_vulnerable(seeds);
+    }
+
+    return [
+        1,
+        board.players_attics[player] - board.players_attics[opponent],
+        own_seeds,
+        opponent_seeds,
+        own_blue_seeds,
+        color_balance,
+        vulnerable_holes,
+        board.legal_moves(player).len() as i32 - board.legal_moves(opponent).len() as i32,
+    ];
+}
+
+/// 1 when a sowing ending in a hole with `seeds` seeds captures them.
+fn is_vulnerable(seeds: i32) -> i32 {
+    return (seeds == 1 || seeds == 2) as i32;
+}
+
+/// The heuristic of the engine, a weighted sum of the `features`.
+pub struct DefaultEvaluator {
+    weights: EvalWeights,
+}
+
+impl DefaultEvaluator {
+    pub fn new(weights: EvalWeights) -> DefaultEvaluator {
+        DefaultEvaluator { weights }
+    }
+
+    pub fn weights(&self) -> &EvalWeights {
+        return &self.weights;
+    }
+}
+
+impl Evaluator for DefaultEvaluator {
+    /// Same as the weighted `features`, without the move generations when
+    /// mobility does not count.
+    fn evaluate(&self, board: &Board, player: usize) -> i32 {
+        let weights = &self.weights;
+        let opponent = (player + 1) % 2;
+        let mut score = weights.contempt + weights.attic_difference * (board.players_attics[player] - board.players_attics[opponent]);
+
+        for hole in (player..16).step_by(2) {
+            let seeds = board.red_holes[hole] + board.blue_holes[hole];
+            score += weights.own_seeds * seeds + weights.own_blue_seeds * board.blue_holes[hole] - weights.vulnerable_holes * is_vulnerable(seeds);
+            score += weights.color_balance * (board.red_holes[hole] - board.blue_holes[hole]);
+        }
+        for hole in (opponent..16).step_by(2) {
+            let seeds = board.red_holes[hole] + board.blue_holes[hole];
+            score += weights.opponent_seeds * seeds + weights.vulnerable_holes * is_vulnerable(seeds);
+            score -= weights.color_balance * (board.red_holes[hole] - board.blue_holes[hole]);
+        }
+
+        if weights.mobility != 0 {
+            let mobility = board.legal_moves(player).len() as i32 - board.legal_moves(opponent).len() as i32;
+            score += weights.mobility * mobility;
+        }
+        return score;
+    }
+}
+
+impl Copy for DefaultEvaluator {}
+
+impl Clone for DefaultEvaluator {
+    fn clone(&self) -> DefaultEvaluator {
+        *self
+    }
+}
+
+impl Default for DefaultEvaluator {
+    fn default() -> DefaultEvaluator {
+        DefaultEvaluator::new(EvalWeights::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::random_moves;
+    use crate::limits::SearchLimits;
+    use crate::minmax::{SearchConfig, Searcher};
+
+    /// Every position is worth the same.
+    struct Flat;
+
+    impl Evaluator for Flat {
+        fn evaluate(&self, _board: &Board, _player: usize) -> i32 {
+            return 0;
+        }
+    }
+
+    #[test]
+    fn test_default_evaluator() {
+        let b = Board::new();
+        let evaluator = DefaultEvaluator::default();
+        assert_eq!(48, evaluator.evaluate(&b, 0));
+        assert_eq!(48, evaluator.evaluate(&b, 1));
+
+        let mut b2 = b;
+        b2.players_attics = [4, 0];
+        assert_eq!(48 + 12, evaluator.evaluate(&b2, 0));
+        assert_eq!(48 - 12, evaluator.evaluate(&b2, 1));
+
+        // Without the contempt and the blue seeds of one side only, the
+        // terms are antisymmetric.
+        let symmetric = DefaultEvaluator::new(EvalWeights { contempt: 0, own_seeds: 1, own_blue_seeds: 0, color_balance: 2, ..EvalWeights::default() });
+        b2.red_holes[0] = 5;
+        b2.blue_holes[3] = 0;
+        // Attics 3 * 4, seeds 35 - 30, colors 2 * (3 - 2).
+        assert_eq!(19, symmetric.evaluate(&b2, 0));
+        assert_eq!(-symmetric.evaluate(&b2, 0), symmetric.evaluate(&b2, 1));
+    }
+
+    #[test]
+    fn test_features() {
+        let mut b = Board::new();
+        let weights = EvalWeights { own_seeds: -3, color_balance: 4, vulnerable_holes: 5, mobility: 2, ..EvalWeights::default() };
+
+        // A whole random game.
+        for (ply, mv) in random_moves(7, usize::MAX).into_iter().enumerate() {
+            for weights in [EvalWeights::default(), weights] {
+                for side in [0, 1] {
+                    let weighted: i32 = features(&b, side).iter().zip(weights.to_array()).map(|(feature, weight)| feature * weight).sum();
+                    assert_eq!(weighted, DefaultEvaluator::new(weights).evaluate(&b, side));
+                }
+            }
+            b.make_move(ply % 2, mv);
+        }
+    }
+
+    #[test]
+    fn test_parse_weights() {
+        let toml = "# tuned\n[weights]\nattic_difference = 5\nmobility = -2 # fewer moves\n\n";
+        let weights = EvalWeights::parse(toml).unwrap();
+        assert_eq!(5, weights.attic_difference);
+        assert_eq!(-2, weights.mobility);
+        assert_eq!(EvalWeights::default().contempt, weights.contempt);
+
+        let json = "{ \"attic_difference\": 5, \"mobility\": -2 }";
+        assert_eq!(weights, EvalWeights::parse(json).unwrap());
+
+        let all = EvalWeights::from_array([1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(all, EvalWeights::parse(&all.to_string()).unwrap());
+        assert_eq!(Some(4), all.get("opponent_seeds"));
+
+        assert!(matches!(EvalWeights::parse("attic = 2"), Err(WeightsError::UnknownTerm(_))));
+        assert!(matches!(EvalWeights::parse("mobility = two"), Err(WeightsError::InvalidValue { .. })));
+        assert!(matches!(EvalWeights::parse("mobility 2"), Err(WeightsError::Syntax(_))));
+        assert!(matches!(EvalWeights::parse("{ mobility: 2 }"), Err(WeightsError::Syntax(_))));
+
+        let mut weights = EvalWeights::default();
+        weights.set_from_str("own_seeds=2").unwrap();
+        assert_eq!(2, weights.own_seeds);
+    }
+
+    #[test]
+    fn test_custom_evaluator() {
+        let b = Board::new();
+        let searcher = Searcher::with_evaluator(SearchConfig::deterministic(), Flat);
+        let result = searcher.search(&b, 0, &SearchLimits::depth(3));
+
+        // No move is better than another : the first one is kept.
+        assert_eq!(Some(b.legal_moves(0)[0]), result.best_move);
+        assert_eq!(0, result.score);
+        assert!(result.root_scores.iter().all(|&(_, score)| score == 0));
+    }
+}
@@ -0,0 +1,27 @@
+//! Oware engine for the 16-hole red/blue variant.
+//!
+//! The crate exposes the board and its rules, the minimax search and a game
+//! session that ties both together. The interactive prompt lives in the
+//! `oware` binary.
+
+#![allow(clippy::needless_return)]
+
+pub mod arena;
+pub mod board;
+pub mod dataset;
+pub mod engine;
+pub mod eval;
+pub mod limits;
+pub mod minmax;
+pub mod protocol;
+pub mod referee;
+pub mod selfplay;
+pub mod tt;
+pub mod tune;
+mod zobrist;
+
+pub use board::{Board, CapturedHole, Color, Move, MoveError, MoveList, MoveOutcome, NotationError, Undo};
+pub use engine::Engine;
+pub use eval::{DefaultEvaluator, EvalWeights, Evaluator};
+pub use limits::{Clock, SearchLimits};
+pub use minmax::{decision_minmax, SearchConfig, SearchResult, Searcher, StopHandle};
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+/// Moves the time manager expects before the end of the game when the clock
+/// does not say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Kept on the clock for the time it takes to send the move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(10);
+
+/// Time left to a player for the rest of the game.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Clock {
+    pub remaining: Duration,
+    /// Added to the clock after each move.
+    pub increment: Duration,
+    /// Moves to play before the next time control, if any.
+    pub moves_to_go: Option<u32>,
+}
+
+impl Clock {
+    pub fn new(remaining: Duration, increment: Duration) -> Clock {
+        Clock { remaining, increment, moves_to_go: None }
+    }
+
+    /// Updates the clock after a move which took `elapsed`. Once the time
+    /// control is over, the clock of the next one is up to the caller.
+    pub fn spend(&mut self, elapsed: Duration) {
+        self.remaining = self.remaining.saturating_sub(elapsed) + self.increment;
+        self.moves_to_go = self.moves_to_go.map(|moves| moves.saturating_sub(1));
+    }
+}
+
+impl Copy for Clock {}
+
+impl Clone for Clock {
+    fn clone(&self) -> Clock {
+        *self
+    }
+}
+
+/// When a search must end. Every limit set applies, the first one reached
+/// stops the search ; without any, only a `StopHandle` does.
+#[derive(PartialEq, Eq, Debug)]
+pub struct SearchLimits {
+    /// Deepest iteration.
+    pub depth: Option<i32>,
+    /// Nodes searched by all the threads together, checked every thousand
+    /// nodes or so.
+    pub nodes: Option<u64>,
+    /// Fixed thinking time.
+    pub movetime: Option<Duration>,
+    /// The time manager takes its share of the clock.
+    pub clock: Option<Clock>,
+}
+
+impl SearchLimits {
+    /// Searches until stopped.
+    pub fn infinite() -> SearchLimits {
+        SearchLimits { depth: None, nodes: None, movetime: None, clock: None }
+    }
+
+    pub fn depth(depth: i32) -> SearchLimits {
+        SearchLimits { depth: Some(depth), ..SearchLimits::infinite() }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits { nodes: Some(nodes), ..SearchLimits::infinite() }
+    }
+
+    pub fn movetime(movetime: Duration) -> SearchLimits {
+        SearchLimits { movetime: Some(movetime), ..SearchLimits::infinite() }
+    }
+
+    pub fn clock(clock: Clock) -> SearchLimits {
+        SearchLimits { clock: Some(clock), ..SearchLimits::infinite() }
+    }
+
+    /// Thinking time given by the time limits, if any.
+    pub fn time_budget(&self) -> Option<TimeBudget> {
+        let from_clock = self.clock.map(|clock| TimeBudget::from_clock(&clock));
+        let fixed = self.movetime.map(|movetime| TimeBudget { optimum: movetime, maximum: movetime });
+        return match (fixed, from_clock) {
+            (Some(fixed), Some(from_clock)) => Some(TimeBudget {
+                optimum: fixed.optimum.min(from_clock.optimum),
+                maximum: fixed.maximum.min(from_clock.maximum),
+            }),
+            (fixed, from_clock) => fixed.or(from_clock),
+        };
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> SearchLimits {
+        SearchLimits::infinite()
+    }
+}
+
+impl Copy for SearchLimits {}
+
+impl Clone for SearchLimits {
+    fn clone(&self) -> SearchLimits {
+        *self
+    }
+}
+
+/// Share of the clock given to one move by the time manager.
+#[derive(PartialEq, Eq, Debug)]
+pub struct TimeBudget {
+    /// No new iteration starts past this time.
+    pub optimum: Duration,
+    /// The iteration in progress is abandoned at this time.
+    pub maximum: Duration,
+}
+
+impl TimeBudget {
+    /// An even share of the clock between the moves to go, plus most of the
+    /// increment. An iteration which started in time may run over it, up to
+    /// three times the share but never more than a third of the clock.
+    fn from_clock(clock: &Clock) -> TimeBudget {
+        let usable = clock.remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let optimum = (usable / moves_to_go + clock.increment * 3 / 4).min(usable);
+        let maximum = (optimum * 3).min((usable / 3).max(optimum));
+        return TimeBudget { optimum, maximum };
+    }
+}
+
+impl Copy for TimeBudget {}
+
+impl Clone for TimeBudget {
+    fn clone(&self) -> TimeBudget {
+        *self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_budget() {
+        assert_eq!(None, SearchLimits::depth(4).time_budget());
+
+        let movetime = Duration::from_millis(300);
+        let budget = SearchLimits::movetime(movetime).time_budget().unwrap();
+        assert_eq!(TimeBudget { optimum: movetime, maximum: movetime }, budget);
+
+        let clock = Clock::new(Duration::from_secs(60), Duration::from_secs(1));
+        let budget = SearchLimits::clock(clock).time_budget().unwrap();
+        assert!(budget.optimum > Duration::from_secs(2) && budget.optimum < Duration::from_secs(3));
+        assert!(budget.maximum > budget.optimum && budget.maximum < Duration::from_secs(20));
+
+        // The last move of the time control may use it all, minus the overhead.
+        let last_move = Clock { moves_to_go: Some(1), ..clock };
+        let budget = SearchLimits::clock(last_move).time_budget().unwrap();
+        assert_eq!(clock.remaining - MOVE_OVERHEAD, budget.optimum);
+        assert_eq!(budget.optimum, budget.maximum);
+
+        // Nearly flagged : never more than what is left.
+        let short = Clock::new(Duration::from_millis(5), Duration::from_secs(1));
+        assert_eq!(Duration::ZERO, SearchLimits::clock(short).time_budget().unwrap().maximum);
+
+        // The tightest of both limits wins.
+        let both = SearchLimits { movetime: Some(Duration::from_millis(100)), ..SearchLimits::clock(clock) };
+        assert_eq!(Duration::from_millis(100), both.time_budget().unwrap().maximum);
+    }
+
+    #[test]
+    fn test_clock_spend() {
+        let mut clock = Clock { moves_to_go: Some(2), ..Clock::new(Duration::from_secs(10), Duration::from_secs(1)) };
+        clock.spend(Duration::from_secs(3));
+        assert_eq!(Duration::from_secs(8), clock.remaining);
+        assert_eq!(Some(1), clock.moves_to_go);
+
+        clock.spend(Duration::from_secs(20));
+        assert_eq!(Duration::from_secs(1), clock.remaining);
+        assert_eq!(Some(0), clock.moves_to_go);
+    }
+}
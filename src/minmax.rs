@@ -1,153 +1,734 @@
 use crate::board::*;
+use crate::eval::*;
+use crate::limits::*;
+use crate::tt::*;
+use crate::zobrist;
 use crossbeam;
 use std::cmp::max;
 use std::cmp::min;
-use std::sync::Mutex;
-use std::time::Instant;
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-struct EvaluatedBoard {
-    board: Board,
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const DEFAULT_TIME_BUDGET: Duration = Duration::from_millis(500);
+
+/// Deepest iteration of the iterative deepening.
+pub const MAX_DEPTH: i32 = 64;
+
+/// Nodes searched between two looks at the clock, a power of two.
+const NODES_BETWEEN_CLOCK_CHECKS: u64 = 1024;
+
+/// Scores above this are wins found by the search, which depend on the ply.
+const WIN_THRESHOLD: i32 = 9_000_000;
+
+struct EvaluatedMove {
+    mv: Move,
     eval: i32,
 }
 
-impl EvaluatedBoard {
-    fn new(board: Board, eval: i32) -> EvaluatedBoard {
-        EvaluatedBoard { board, eval }
+impl EvaluatedMove {
+    fn new(mv: Move, eval: i32) -> EvaluatedMove {
+        EvaluatedMove { mv, eval }
     }
 }
 
-impl Copy for EvaluatedBoard {}
+impl Copy for EvaluatedMove {}
 
-impl Clone for EvaluatedBoard {
-    fn clone(&self) -> EvaluatedBoard {
+impl Clone for EvaluatedMove {
+    fn clone(&self) -> EvaluatedMove {
         *self
     }
 }
 
-pub fn decision_minmax(board: &Board, player: usize) -> (Color, usize) {
-    const THREAD_AMOUNT: usize = 4;
-    let mut max_depth;
-    let now = Instant::now();
+/// What the threads of one search share.
+struct SharedSearch<'a, E: Evaluator> {
+    tt: &'a TranspositionTable,
+    evaluator: &'a E,
+    /// Set by the search itself, to abandon the iteration in progress.
+    stop: AtomicBool,
+    /// Set from outside the search.
+    stop_handle: &'a StopHandle,
+    /// No new iteration starts past this time.
+    soft_deadline: Option<Instant>,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    cpt: Mutex<u64>,
+    cpt_cut: Mutex<u64>,
+    /// Nodes searched so far by all the threads, updated at each clock check
+    /// and after each root move.
+    progress: AtomicU64,
+    start: Instant,
+}
 
-    let cpt = Mutex::new(0);
-    let cpt_cut = Mutex::new(0);
-    let values = Mutex::new([[0; 16]; 2]);
+impl<E: Evaluator> SharedSearch<'_, E> {
+    fn should_go_on(&self) -> bool {
+        return !self.stop_handle.is_stopped()
+            && self.deadline.is_none_or(|deadline| Instant::now() < deadline)
+            && self.max_nodes.is_none_or(|max_nodes| self.progress.load(Ordering::Relaxed) < max_nodes);
+    }
 
-    let indexs_per_threads = Mutex::new([(); THREAD_AMOUNT].map(|_| Vec::new()));
-    let mut thread_index = 0;
-    let mut moves_amount = 0;
+    fn can_start_iteration(&self) -> bool {
+        return self.should_go_on() && self.soft_deadline.is_none_or(|deadline| Instant::now() < deadline);
+    }
 
-    for color in 0..2 {
-        for hole in 0..16 {
-            if board.is_this_move_possible(player, hole, Color::from_integer(color)) {
-                indexs_per_threads.lock().unwrap()[thread_index].push((color, hole));
-                thread_index = (thread_index + 1) % THREAD_AMOUNT;
-                moves_amount += 1;
-            } else {
-                values.lock().unwrap()[color][hole] = i32::MIN;
-            }
+    fn add_counters(&self, search: &ThreadSearch<E>) {
+        *self.cpt.lock().unwrap() += search.cpt;
+        *self.cpt_cut.lock().unwrap() += search.cpt_cut;
+    }
+}
+
+/// Longest line : the deepest iteration plus the root.
+const PV_SIZE: usize = MAX_DEPTH as usize + 2;
+
+/// Search state of one thread.
+struct ThreadSearch<'a, E: Evaluator> {
+    shared: &'a SharedSearch<'a, E>,
+    max_player: usize,
+    max_depth: i32,
+    cpt: u64,
+    cpt_cut: u64,
+    /// Part of `cpt` already added to the shared progress.
+    reported: u64,
+    /// Triangular table : `pv[ply]` is the best line found from the node at `ply`.
+    pv: Box<[[Move; PV_SIZE]; PV_SIZE]>,
+    pv_len: [usize; PV_SIZE],
+}
+
+impl<'a, E: Evaluator> ThreadSearch<'a, E> {
+    fn new(shared: &'a SharedSearch<'a, E>, max_player: usize, max_depth: i32) -> ThreadSearch<'a, E> {
+        ThreadSearch {
+            shared,
+            max_player,
+            max_depth,
+            cpt: 0,
+            cpt_cut: 0,
+            reported: 0,
+            pv: Box::new([[Move::new(0, Color::Red); PV_SIZE]; PV_SIZE]),
+            pv_len: [0; PV_SIZE],
         }
     }
 
-    if moves_amount > 12 {
-        max_depth = 9;
-    } else if moves_amount > 1 {
-        max_depth = 10;
-    } else {
-        max_depth = 0;
+    /// `mv` is the new best move at `ply`, followed by the best line of its child.
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let child_len = self.pv_len[ply + 1];
+        let (lines, child_lines) = self.pv.split_at_mut(ply + 1);
+        lines[ply][0] = mv;
+        lines[ply][1..=child_len].copy_from_slice(&child_lines[0][..child_len]);
+        self.pv_len[ply] = child_len + 1;
     }
-    
-    println!("Starting turn...");
-    while now.elapsed().as_secs_f32() <= 0.5 {
-        crossbeam::scope(|scope| {
-            for ipt in 0..THREAD_AMOUNT {
-                let cpt = &cpt;
-                let cpt_cut = &cpt_cut;
-                let indexs_per_threads = &indexs_per_threads;
-                let values = &values;
-                
-                scope.spawn(move |_| {
-                    let ipt_len = indexs_per_threads.lock().unwrap()[ipt].len();
-                    for t in 0..ipt_len {
-                        let color = indexs_per_threads.lock().unwrap()[ipt][t].0;
-                        let hole = indexs_per_threads.lock().unwrap()[ipt][t].1;
+
+    /// Once stopped, every node returns at once and its score is meaningless.
+    fn is_stopped(&self) -> bool {
+        return self.shared.stop.load(Ordering::Relaxed);
+    }
+
+    /// Adds the nodes searched since the last call to the shared progress.
+    fn report_progress(&mut self) {
+        self.shared.progress.fetch_add(self.cpt - self.reported, Ordering::Relaxed);
+        self.reported = self.cpt;
+    }
+
+    fn check_deadline(&mut self) {
+        self.report_progress();
+        if !self.shared.should_go_on() {
+            self.shared.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A root move with its score and line at the last completed depth.
+struct RootMove {
+    mv: Move,
+    score: i32,
+    pv: Vec<Move>,
+}
+
+impl RootMove {
+    fn new(mv: Move) -> RootMove {
+        RootMove { mv, score: i32::MIN, pv: vec![mv] }
+    }
+}
+
+impl Clone for RootMove {
+    fn clone(&self) -> RootMove {
+        RootMove { mv: self.mv, score: self.score, pv: self.pv.clone() }
+    }
+}
+
+/// What the search found, scores being seen by the searching player.
+#[derive(Debug)]
+pub struct SearchResult {
+    /// `None` when the player has no legal move : the game is over, the
+    /// line and the root scores are empty and the score is the one of the
+    /// final position.
+    pub best_move: Option<Move>,
+    pub score: i32,
+    /// Principal variation : the best move followed by the expected replies.
+    pub pv: Vec<Move>,
+    /// Last completed depth, 0 when the search could not finish one.
+    pub depth: i32,
+    pub nodes: u64,
+    pub cuts: u64,
+    pub elapsed: Duration,
+    /// Score of every root move at the last completed depth, best first.
+    /// Empty when no depth was completed.
+    pub root_scores: Vec<(Move, i32)>,
+}
+
+/// Stops a search from another thread. The search then returns the best move
+/// of the last completed depth, as when its time runs out.
+pub struct StopHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    pub fn new() -> StopHandle {
+        StopHandle { stop: Arc::new(AtomicBool::new(false)) }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        return self.stop.load(Ordering::Relaxed);
+    }
+}
+
+impl Default for StopHandle {
+    fn default() -> StopHandle {
+        StopHandle::new()
+    }
+}
+
+/// The clones stop the same search.
+impl Clone for StopHandle {
+    fn clone(&self) -> StopHandle {
+        StopHandle { stop: Arc::clone(&self.stop) }
+    }
+}
+
+/// Progress of a search once a depth is completed.
+pub struct SearchInfo<'a> {
+    pub depth: i32,
+    /// Score of the best move, seen by the searching player.
+    pub score: i32,
+    pub pv: &'a [Move],
+    /// Nodes searched so far, the helper threads of the lazy SMP mode being
+    /// counted every thousand nodes or so.
+    pub nodes: u64,
+    /// Nodes per second since the search started.
+    pub nps: u64,
+    pub elapsed: Duration,
+}
+
+/// Told about every completed depth, e.g. to print the search as it goes.
+/// Called by the thread which started the search.
+pub trait SearchObserver {
+    fn on_iteration(&mut self, info: &SearchInfo);
+}
+
+/// Nothing to report.
+impl SearchObserver for () {
+    fn on_iteration(&mut self, _info: &SearchInfo) {}
+}
+
+impl<F: FnMut(&SearchInfo)> SearchObserver for F {
+    fn on_iteration(&mut self, info: &SearchInfo) {
+        self(info);
+    }
+}
+
+/// How the threads share the work.
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParallelMode {
+    /// The root moves are dealt to the threads, one at a time.
+    RootSplit,
+    /// Every thread searches the whole tree and they share the transposition
+    /// table, so it scales past the number of root moves.
+    LazySmp,
+}
+
+impl Copy for ParallelMode {}
+
+impl Clone for ParallelMode {
+    fn clone(&self) -> ParallelMode {
+        *self
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ParseModeError;
+
+impl fmt::Display for ParseModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected root-split or lazy-smp")
+    }
+}
+
+impl std::error::Error for ParseModeError { }
+
+impl FromStr for ParallelMode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<ParallelMode, ParseModeError> {
+        return match s {
+            "root-split" => Ok(ParallelMode::RootSplit),
+            "lazy-smp" => Ok(ParallelMode::LazySmp),
+            _ => Err(ParseModeError)
+        };
+    }
+}
+
+/// The cores of the machine, 1 when unknown.
+pub fn available_cores() -> usize {
+    return std::thread::available_parallelism().map_or(1, |n| n.get());
+}
+
+pub struct SearchConfig {
+    /// Search threads, the available cores by default.
+    pub threads: usize,
+    /// Size of the transposition table in megabytes.
+    pub hash_mb: usize,
+    pub mode: ParallelMode,
+}
+
+impl SearchConfig {
+    /// A single thread : searched with a depth or node limit from a fresh or
+    /// cleared table, a position always gets the same move, score and line.
+    /// Ties keep the order of `Board::legal_moves`.
+    pub fn deterministic() -> SearchConfig {
+        SearchConfig { threads: 1, hash_mb: DEFAULT_HASH_MB, mode: ParallelMode::RootSplit }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        SearchConfig {
+            threads: available_cores(),
+            hash_mb: DEFAULT_HASH_MB,
+            mode: ParallelMode::RootSplit,
+        }
+    }
+}
+
+impl Copy for SearchConfig {}
+
+impl Clone for SearchConfig {
+    fn clone(&self) -> SearchConfig {
+        *self
+    }
+}
+
+/// Owns what the search keeps from one move to the next : its configuration
+/// and the transposition table shared by all the search threads.
+pub struct Searcher<E: Evaluator = DefaultEvaluator> {
+    config: SearchConfig,
+    tt: TranspositionTable,
+    evaluator: E,
+}
+
+impl Searcher {
+    pub fn new(config: SearchConfig) -> Searcher {
+        Searcher::with_evaluator(config, DefaultEvaluator::default())
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    pub fn with_evaluator(config: SearchConfig, evaluator: E) -> Searcher<E> {
+        Searcher { config, tt: TranspositionTable::new(config.hash_mb), evaluator }
+    }
+
+    pub fn evaluator(&self) -> &E {
+        return &self.evaluator;
+    }
+
+    pub fn config(&self) -> &SearchConfig {
+        return &self.config;
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.config.threads = threads.max(1);
+    }
+
+    /// Reallocates the transposition table, forgetting its content.
+    pub fn set_hash_size(&mut self, hash_mb: usize) {
+        self.config.hash_mb = hash_mb;
+        self.tt = TranspositionTable::new(hash_mb);
+    }
+
+    pub fn tt(&self) -> &TranspositionTable {
+        return &self.tt;
+    }
+
+    /// Forgets every position, e.g. when a new game starts.
+    pub fn clear(&self) {
+        self.tt.clear();
+    }
+
+    /// Iterative deepening until one of the `limits` is reached. The
+    /// iteration in progress then is abandoned and the best move of the last
+    /// completed depth is returned.
+    pub fn search(&self, board: &Board, player: usize, limits: &SearchLimits) -> SearchResult {
+        return self.search_with_observer(board, player, limits, &mut ());
+    }
+
+    /// Same as `search`, `observer` being told about every completed depth.
+    pub fn search_with_observer(&self, board: &Board, player: usize, limits: &SearchLimits, observer: &mut dyn SearchObserver) -> SearchResult {
+        return self.search_with_stop(board, player, limits, &StopHandle::new(), observer);
+    }
+
+    /// Same as `search_with_observer`, the search also ending as soon as
+    /// `stop` is stopped. With infinite limits, only `stop` ends it.
+    pub fn search_with_stop(
+        &self,
+        board: &Board,
+        player: usize,
+        limits: &SearchLimits,
+        stop: &StopHandle,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchResult {
+        return self.iterative_deepening(board, player, limits, stop, observer);
+    }
+
+    fn iterative_deepening(
+        &self,
+        board: &Board,
+        player: usize,
+        limits: &SearchLimits,
+        stop_handle: &StopHandle,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchResult {
+        self.tt.new_search();
+        let now = Instant::now();
+        let budget = limits.time_budget();
+        let shared = SharedSearch {
+            tt: &self.tt,
+            evaluator: &self.evaluator,
+            stop: AtomicBool::new(false),
+            stop_handle,
+            soft_deadline: budget.and_then(|budget| now.checked_add(budget.optimum)),
+            deadline: budget.and_then(|budget| now.checked_add(budget.maximum)),
+            max_nodes: limits.nodes,
+            cpt: Mutex::new(0),
+            cpt_cut: Mutex::new(0),
+            progress: AtomicU64::new(0),
+            start: now,
+        };
+        let depth_limit = limits.depth.map_or(MAX_DEPTH, |depth| depth.min(MAX_DEPTH));
+
+        let mut root_moves: Vec<RootMove> = board.legal_moves(player).iter().map(|&mv| RootMove::new(mv)).collect();
+        if root_moves.is_empty() {
+            return SearchResult {
+                best_move: None,
+                score: evaluation(board, &self.evaluator, player, 0),
+                pv: Vec::new(),
+                depth: 0,
+                nodes: 0,
+                cuts: 0,
+                elapsed: now.elapsed(),
+                root_scores: Vec::new(),
+            };
+        }
+        let mut completed_depth = 0;
+        if root_moves.len() > 1 {
+            completed_depth = match self.config.mode {
+                ParallelMode::RootSplit => self.root_split(board, player, &mut root_moves, &shared, depth_limit, observer),
+                ParallelMode::LazySmp => self.lazy_smp(board, player, &mut root_moves, &shared, depth_limit, observer),
+            };
+        }
+
+        let best = &root_moves[0];
+        let mut pv = best.pv.clone();
+        let score = if completed_depth > 0 {
+            extend_pv(board, player, &mut pv, &self.tt, completed_depth + 1);
+            best.score
+        } else {
+            let mut new_board = *board;
+            new_board.make_move(player, best.mv);
+            evaluation(&new_board, &self.evaluator, player, 0)
+        };
+
+        let root_scores = if completed_depth > 0 {
+            root_moves.iter().map(|root_move| (root_move.mv, root_move.score)).collect()
+        } else {
+            Vec::new()
+        };
+
+        let nodes = *shared.cpt.lock().unwrap();
+        let cuts = *shared.cpt_cut.lock().unwrap();
+        return SearchResult {
+            best_move: Some(best.mv),
+            score,
+            pv,
+            depth: completed_depth,
+            nodes,
+            cuts,
+            elapsed: now.elapsed(),
+            root_scores,
+        };
+    }
+
+    /// The root moves of an iteration are a shared queue, best move of the
+    /// previous iteration first : each thread takes the next one as soon as
+    /// it is done with its own. Returns the last completed depth.
+    fn root_split(&self, board: &Board, player: usize, root_moves: &mut [RootMove], shared: &SharedSearch<E>, depth_limit: i32, observer: &mut dyn SearchObserver) -> i32 {
+        let moves_amount = root_moves.len();
+        let thread_amount = self.config.threads.clamp(1, moves_amount);
+        let mut completed_depth = 0;
+
+        let mut max_depth = 1;
+        while max_depth <= depth_limit && shared.can_start_iteration() {
+            let results = Mutex::new(vec![(i32::MIN, Vec::new()); moves_amount]);
+            let next_index = AtomicUsize::new(0);
+
+            crossbeam::scope(|scope| {
+                for _ in 0..thread_amount {
+                    let next_index = &next_index;
+                    let root_moves = &*root_moves;
+                    let results = &results;
+
+                    scope.spawn(move |_| {
                         let mut new_board = *board;
+                        let mut search = ThreadSearch::new(shared, player, max_depth);
+                        loop {
+                            let index = next_index.fetch_add(1, Ordering::Relaxed);
+                            if index >= moves_amount {
+                                break;
+                            }
+                            let result = search_root_move(&mut new_board, &mut search, player, root_moves[index].mv);
+                            if search.is_stopped() {
+                                break;
+                            }
+                            results.lock().unwrap()[index] = result;
+                        }
+                        shared.add_counters(&search);
+                    });
+                }
+            }).unwrap();
 
-                        new_board.play_move(player, hole, Color::from_integer(color));
-                        let mut local_cpt = 0;
-                        let mut local_cpt_cut = 0;
-                        let eval = minimax_alphabeta(
-                            &new_board,
-                            player,
-                            (player + 1) % 2,
-                            false,
-                            0,
-                            max_depth,
-                            -10_000_000,
-                            10_000_000,
-                            &mut local_cpt,
-                            &mut local_cpt_cut,
-                        );
-                        values.lock().unwrap()[color][hole] = eval;
-                        *cpt.lock().unwrap() += local_cpt;
-                        *cpt_cut.lock().unwrap() += local_cpt_cut;
-                    }
-                });
+            if shared.stop.load(Ordering::Relaxed) {
+                break;
             }
-        }).unwrap();
-        max_depth += 1;
-        if now.elapsed().as_secs_f32() < 0.275 {
+
+            for (root_move, (score, pv)) in root_moves.iter_mut().zip(results.into_inner().unwrap()) {
+                root_move.score = score;
+                root_move.pv = pv;
+            }
+            sort_root_moves(root_moves);
+            completed_depth = max_depth;
+            report_iteration(observer, board, player, &root_moves[0], shared, completed_depth);
             max_depth += 1;
         }
+
+        return completed_depth;
     }
 
-    let mut indices = (0, 0);
-    let mut max_value = values.lock().unwrap()[indices.0][indices.1];
-    for color in 0..2 {
-        for hole in 0..16 {
-            let value = values.lock().unwrap()[color][hole];
-            if value > max_value {
-                indices.0 = color;
-                indices.1 = hole;
-                max_value = value;
+    /// Every thread searches the whole tree, the helpers one ply deeper every
+    /// other thread and with their root moves rotated, so that they fill the
+    /// shared table with positions the main thread reaches soon after. Only
+    /// the iterations of the main thread (the calling one) count.
+    fn lazy_smp(&self, board: &Board, player: usize, root_moves: &mut [RootMove], shared: &SharedSearch<E>, depth_limit: i32, observer: &mut dyn SearchObserver) -> i32 {
+        let moves_amount = root_moves.len();
+        let mut completed_depth = 0;
+
+        crossbeam::scope(|scope| {
+            for helper in 1..self.config.threads.max(1) {
+                let mut helper_moves = root_moves.to_vec();
+                helper_moves.rotate_left(helper % moves_amount);
+
+                scope.spawn(move |_| {
+                    let mut new_board = *board;
+                    let mut search = ThreadSearch::new(shared, player, 1 + (helper % 2) as i32);
+                    while search.max_depth <= depth_limit && !search.is_stopped() {
+                        for root_move in helper_moves.iter_mut() {
+                            root_move.score = search_root_move(&mut new_board, &mut search, player, root_move.mv).0;
+                        }
+                        if !search.is_stopped() {
+                            sort_root_moves(&mut helper_moves);
+                        }
+                        search.max_depth += 1;
+                    }
+                    shared.add_counters(&search);
+                });
             }
-        }
+
+            let mut new_board = *board;
+            let mut search = ThreadSearch::new(shared, player, 1);
+            while search.max_depth <= depth_limit && shared.can_start_iteration() {
+                let mut results = Vec::with_capacity(moves_amount);
+                for root_move in root_moves.iter() {
+                    results.push(search_root_move(&mut new_board, &mut search, player, root_move.mv));
+                    if search.is_stopped() {
+                        break;
+                    }
+                }
+                if search.is_stopped() {
+                    break;
+                }
+
+                for (root_move, (score, pv)) in root_moves.iter_mut().zip(results) {
+                    root_move.score = score;
+                    root_move.pv = pv;
+                }
+                sort_root_moves(root_moves);
+                completed_depth = search.max_depth;
+                report_iteration(observer, board, player, &root_moves[0], shared, completed_depth);
+                search.max_depth += 1;
+            }
+            shared.add_counters(&search);
+
+            // Done : the helpers must not outlive the main thread.
+            shared.stop.store(true, Ordering::Relaxed);
+        }).unwrap();
+
+        return completed_depth;
     }
+}
+
+impl Default for Searcher {
+    fn default() -> Searcher {
+        Searcher::new(SearchConfig::default())
+    }
+}
 
-    println!("Depth : {}, for {} move(s) available.", max_depth, moves_amount);
-    println!(
-        "{} minimax calls, with {} alphabeta cuts.\nElapsed time : {}s",
-        cpt.lock().unwrap(),
-        cpt_cut.lock().unwrap(),
-        now.elapsed().as_secs_f32()
-    );
+/// Score of a root move and the line it starts.
+fn search_root_move<E: Evaluator>(board: &mut Board, search: &mut ThreadSearch<E>, player: usize, mv: Move) -> (i32, Vec<Move>) {
+    let undo = board.make_move(player, mv);
+    let eval = minimax_alphabeta(board, search, (player + 1) % 2, false, 0, -10_000_000, 10_000_000);
+    board.unmake_move(&undo);
+    search.report_progress();
 
-    return (Color::from_integer(indices.0), indices.1);
+    search.update_pv(0, mv);
+    return (eval, search.pv[0][..search.pv_len[0]].to_vec());
 }
 
-fn minimax_alphabeta(board: &Board, max_player: usize, player: usize, is_max: bool, depth: i32, max_depth: i32, alpha: i32, beta: i32, cpt: &mut i32, cpt_cut: &mut i32) -> i32 {
-   *cpt += 1;
+fn report_iteration<E: Evaluator>(observer: &mut dyn SearchObserver, board: &Board, player: usize, best: &RootMove, shared: &SharedSearch<E>, depth: i32) {
+    let mut pv = best.pv.clone();
+    extend_pv(board, player, &mut pv, shared.tt, depth + 1);
+
+    let nodes = shared.progress.load(Ordering::Relaxed);
+    let elapsed = shared.start.elapsed();
+    let nps = (nodes as f64 / elapsed.as_secs_f64().max(1e-6)) as u64;
+    observer.on_iteration(&SearchInfo { depth, score: best.score, pv: &pv, nodes, nps, elapsed });
+}
+
+/// Sorts the root moves by decreasing score, the next iteration searches the best one first.
+fn sort_root_moves(root_moves: &mut [RootMove]) {
+    root_moves.sort_by_key(|root_move| Reverse(root_move.score));
+}
+
+/// The line stops where a table hit cut the search, the table often knows how
+/// it goes on. An iteration at `max_depth` plays up to `max_depth + 1` moves.
+fn extend_pv(board: &Board, player: usize, pv: &mut Vec<Move>, tt: &TranspositionTable, plies: i32) {
+    let max_player = player;
+    let mut board = *board;
+    let mut player = player;
+    for &mv in pv.iter() {
+        board.make_move(player, mv);
+        player = (player + 1) % 2;
+    }
+
+    while (pv.len() as i32) < plies && !board.is_final_position() {
+        let mv = match tt.probe(tt_key(&board, max_player)).and_then(|entry| entry.best_move) {
+            Some(mv) if board.is_this_move_possible(player, mv.hole, mv.color) => mv,
+            _ => break,
+        };
+        pv.push(mv);
+        board.make_move(player, mv);
+        player = (player + 1) % 2;
+    }
+}
+
+pub fn format_line(line: &[Move]) -> String {
+    return line.iter().map(|mv| mv.to_string()).collect::<Vec<String>>().join(" ");
+}
+
+/// Searches for `time_budget` with a fresh `Searcher`, see `Searcher::search`.
+pub fn decision_minmax(board: &Board, player: usize, time_budget: Duration) -> SearchResult {
+    return Searcher::default().search(board, player, &SearchLimits::movetime(time_budget));
+}
+
+/* A score only holds for the player the search is for, see `EvalWeights` :
+ * the table keys of one player differ from the other player's. The table
+ * stores the scores as seen by that player, wins counting their plies from
+ * the stored position. */
+pub(crate) fn tt_key(board: &Board, max_player: usize) -> u64 {
+    return board.hash() ^ zobrist::searching_player(max_player);
+}
+
+fn score_to_tt(score: i32, depth: i32) -> i32 {
+    if score > WIN_THRESHOLD {
+        return score + depth;
+    }
+    if score < -WIN_THRESHOLD {
+        return score - depth;
+    }
+    return score;
+}
+
+fn score_from_tt(score: i32, depth: i32) -> i32 {
+    if score > WIN_THRESHOLD {
+        return score - depth;
+    }
+    if score < -WIN_THRESHOLD {
+        return score + depth;
+    }
+    return score;
+}
+
+fn minimax_alphabeta<E: Evaluator>(board: &mut Board, search: &mut ThreadSearch<E>, player: usize, is_max: bool, depth: i32, alpha: i32, beta: i32) -> i32 {
+    search.cpt += 1;
+    if search.cpt & (NODES_BETWEEN_CLOCK_CHECKS - 1) == 0 {
+        search.check_deadline();
+    }
+    if search.is_stopped() {
+        return 0;
+    }
     let mut alpha = alpha;
     let mut beta = beta;
+    let max_player = search.max_player;
+    let ply = depth as usize + 1;
+    search.pv_len[ply] = 0;
 
-    if depth == max_depth || board.is_final_position() {
-        return evaluation(board, max_player, depth);
+    if depth == search.max_depth || board.is_final_position() {
+        return evaluation(board, search.shared.evaluator, max_player, depth);
     }
 
-    let mut moves = [EvaluatedBoard::new(*board, i32::MIN); 32];
-    let mut move_index = 0;
-    for color in 0..2 {
-        for hole in 0..16 {
-            if board.is_this_move_possible(player, hole, Color::from_integer(color)) {
-                moves[move_index]
-                    .board
-                    .play_move(player, hole, Color::from_integer(color));
-                moves[move_index].eval = evaluation(&moves[move_index].board, max_player, depth);
+    let remaining_depth = search.max_depth - depth;
+    let mut tt_move = None;
+    if let Some(entry) = search.shared.tt.probe(tt_key(board, max_player)) {
+        tt_move = entry.best_move;
+        if entry.depth >= remaining_depth {
+            let score = score_from_tt(entry.score, depth);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower => alpha = max(alpha, score),
+                Bound::Upper => beta = min(beta, score),
+            }
+            if alpha >= beta {
+                search.cpt_cut += 1;
+                return score;
             }
-            move_index += 1;
         }
     }
-    for i in 0..32 {
+    let alpha_start = alpha;
+    let beta_start = beta;
+
+    let legal_moves = board.legal_moves(player);
+    let mut moves = [EvaluatedMove::new(Move::new(0, Color::Red), i32::MIN); MAX_MOVES];
+    for (evaluated, &mv) in moves.iter_mut().zip(&legal_moves) {
+        let undo = board.make_move(player, mv);
+        *evaluated = EvaluatedMove::new(mv, evaluation(board, search.shared.evaluator, max_player, depth));
+        board.unmake_move(&undo);
+    }
+    let moves = &mut moves[..legal_moves.len()];
+
+    for i in 0..moves.len() {
         let x_ev = moves[i];
         let mut j = i;
         while j > 0
@@ -164,41 +745,72 @@ fn minimax_alphabeta(board: &Board, max_player: usize, player: usize, is_max: bo
         moves[j] = x_ev;
     }
 
+    if let Some(tt_move) = tt_move {
+        if let Some(position) = moves.iter().position(|m| m.mv == tt_move) {
+            moves[..=position].rotate_right(1);
+        }
+    }
+
     let mut value: i32;
+    let mut best_move = moves[0].mv;
     if is_max {
         value = -10_000_000;
-        for i in 0..32 {
-            if moves[i].eval > i32::MIN {
-                let eval = minimax_alphabeta(&moves[i].board, max_player, (player + 1) % 2, false,
-                    depth + 1, max_depth, alpha, beta, cpt, cpt_cut);
-                value = max(value, eval);
-                if value >= beta {
-                    *cpt_cut += 1;
-                    break;
-                }
-                alpha = max(alpha, value);
+        for m in moves.iter() {
+            let undo = board.make_move(player, m.mv);
+            let eval = minimax_alphabeta(board, search, (player + 1) % 2, false,
+                depth + 1, alpha, beta);
+            board.unmake_move(&undo);
+            if search.is_stopped() {
+                return 0;
+            }
+            if eval > value {
+                value = eval;
+                best_move = m.mv;
+                search.update_pv(ply, m.mv);
             }
+            if value >= beta {
+                search.cpt_cut += 1;
+                break;
+            }
+            alpha = max(alpha, value);
         }
     } else {
         value = 10_000_000;
-        for i in 0..32 {
-            if moves[i].eval > i32::MIN {
-                let eval = minimax_alphabeta(&moves[i].board, max_player, (player + 1) % 2, true,
-                    depth + 1, max_depth, alpha, beta, cpt, cpt_cut);
-                value = min(value, eval);
-                if alpha >= value {
-                    *cpt_cut += 1;
-                    break;
-                }
-                beta = min(beta, value);
+        for m in moves.iter() {
+            let undo = board.make_move(player, m.mv);
+            let eval = minimax_alphabeta(board, search, (player + 1) % 2, true,
+                depth + 1, alpha, beta);
+            board.unmake_move(&undo);
+            if search.is_stopped() {
+                return 0;
+            }
+            if eval < value {
+                value = eval;
+                best_move = m.mv;
+                search.update_pv(ply, m.mv);
+            }
+            if alpha >= value {
+                search.cpt_cut += 1;
+                break;
             }
+            beta = min(beta, value);
         }
     }
 
+    let bound = if value <= alpha_start {
+        Bound::Upper
+    } else if value >= beta_start {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    search.shared.tt.store(tt_key(board, max_player), remaining_depth, bound, score_to_tt(value, depth), Some(best_move));
+
     return value;
 }
 
-fn evaluation(board: &Board, max_player: usize, depth: i32) -> i32 {
+/// Final positions are scored by the search, the others by the evaluator.
+fn evaluation<E: Evaluator>(board: &Board, evaluator: &E, max_player: usize, depth: i32) -> i32 {
     if board.is_winning(max_player) {
         return 10_000_000 - depth;
     }
@@ -208,69 +820,317 @@ fn evaluation(board: &Board, max_player: usize, depth: i32) -> i32 {
     if board.is_draw() {
         return 0;
     }
-
-    let opponent = (max_player + 1) % 2;
-    let diff_seed_attic = board.players_attics[max_player] - board.players_attics[opponent];
-
-    let nb_red_seed_pair = board.red_holes[0]
-        + board.red_holes[2]
-        + board.red_holes[4]
-        + board.red_holes[6]
-        + board.red_holes[8]
-        + board.red_holes[10]
-        + board.red_holes[12]
-        + board.red_holes[14];
-    let nb_blue_seed_pair = board.blue_holes[0]
-        + board.blue_holes[2]
-        + board.blue_holes[4]
-        + board.blue_holes[6]
-        + board.blue_holes[8]
-        + board.blue_holes[10]
-        + board.blue_holes[12]
-        + board.blue_holes[14];
-    let nb_seed_pair = nb_red_seed_pair + nb_blue_seed_pair;
-
-    let nb_red_seed_impair = board.red_holes[1]
-        + board.red_holes[3]
-        + board.red_holes[5]
-        + board.red_holes[7]
-        + board.red_holes[9]
-        + board.red_holes[11]
-        + board.red_holes[13]
-        + board.red_holes[15];
-    let nb_blue_seed_impair = board.blue_holes[1]
-        + board.blue_holes[3]
-        + board.blue_holes[5]
-        + board.blue_holes[7]
-        + board.blue_holes[9]
-        + board.blue_holes[11]
-        + board.blue_holes[13]
-        + board.blue_holes[15];
-    let nb_seed_impair = nb_red_seed_impair + nb_blue_seed_impair;
-
-    if max_player == 0 {
-        return diff_seed_attic * 3 + (64 - nb_seed_impair) + (nb_blue_seed_pair);
-    } else {
-        return diff_seed_attic * 3 + (64 - nb_seed_pair) + (nb_blue_seed_impair);
-    }
+    return evaluator.evaluate(board, max_player);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn play_line(line: &str) -> (Board, usize) {
+        let mut board = Board::new();
+        let mut player = 0;
+        for mv in line.split_whitespace() {
+            board.try_play_move(player, mv.parse().unwrap()).unwrap();
+            player = (player + 1) % 2;
+        }
+        return (board, player);
+    }
+
+    /// Same move, score, line and node count, run after run.
+    #[test]
+    fn test_deterministic_search() {
+        let (b, player) = play_line("1R 14B 11B 8B 5B 4R");
+        for limits in [SearchLimits::depth(5), SearchLimits::nodes(20_000)] {
+            let first = Searcher::new(SearchConfig::deterministic()).search(&b, player, &limits);
+            for _ in 0..2 {
+                let searcher = Searcher::new(SearchConfig::deterministic());
+                let result = searcher.search(&b, player, &limits);
+                assert_eq!(first.best_move, result.best_move);
+                assert_eq!(first.score, result.score);
+                assert_eq!(first.pv, result.pv);
+                assert_eq!(first.nodes, result.nodes);
+                assert_eq!(first.root_scores, result.root_scores);
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_positions() {
+        let positions = [
+            ("", "1B", 48),
+            ("1R", "14B", 59),
+            ("1R 14B 11B 8B 5B 4R", "15B", 57),
+            ("1B 4R 15R 12B 9B 6R 3B 16R 13R 10B", "7B", 49),
+        ];
+        for (line, best_move, score) in positions {
+            let (b, player) = play_line(line);
+            let result = Searcher::new(SearchConfig::deterministic()).search(&b, player, &SearchLimits::depth(6));
+            assert_eq!(best_move.parse::<Move>().ok(), result.best_move, "after {:?}", line);
+            assert_eq!(score, result.score, "after {:?}", line);
+        }
+
+        let result = Searcher::new(SearchConfig::deterministic()).search(&Board::new(), 0, &SearchLimits::nodes(20_000));
+        assert_eq!("1R".parse::<Move>().ok(), result.best_move);
+        assert_eq!(49, result.score);
+
+        // Capturing 1R + 2B reaches 33 seeds and wins at once.
+        let mut b = Board::new();
+        b.red_holes = [0; 16];
+        b.blue_holes = [0; 16];
+        b.red_holes[0] = 1;
+        b.blue_holes[1] = 2;
+        b.red_holes[6] = 2;
+        b.blue_holes[4] = 1;
+        b.blue_holes[9] = 3;
+        b.red_holes[12] = 1;
+        b.players_attics = [30, 30];
+        b.refresh_hash();
+        let result = Searcher::new(SearchConfig::deterministic()).search(&b, 0, &SearchLimits::depth(6));
+        assert_eq!(Some(Move::new(0, Color::Red)), result.best_move);
+        assert!(result.score > WIN_THRESHOLD);
+    }
+
+    /// The entries of a search for one player do not mislead a search for the
+    /// other one.
+    #[test]
+    fn test_table_shared_by_both_players() {
+        let searcher = Searcher::new(SearchConfig::deterministic());
+        let first = searcher.search(&Board::new(), 0, &SearchLimits::depth(7));
+        let mut b = Board::new();
+        b.make_move(0, first.best_move.unwrap());
+
+        let warmed = searcher.search(&b, 1, &SearchLimits::depth(6));
+        let fresh = Searcher::new(SearchConfig::deterministic()).search(&b, 1, &SearchLimits::depth(6));
+        assert_eq!(fresh.best_move, warmed.best_move);
+        assert_eq!(fresh.score, warmed.score);
+        assert_eq!(fresh.root_scores, warmed.root_scores);
+    }
+
     #[test]
     fn test_decision_minmax() {
         let mut b = Board::new();
         b.play_move(0, 0, Color::Red);
-        let indice = decision_minmax(&b, 1);
-        println!(
-            "{} {}",
-            (indice.1) + 1,
-            match indice.0 {
-                Color::Red => "R",
-                Color::Blue => "B",
+        let mv = decision_minmax(&b, 1, DEFAULT_TIME_BUDGET).best_move.unwrap();
+        assert!(b.is_this_move_possible(1, mv.hole, mv.color));
+        println!("{}", mv);
+    }
+
+    #[test]
+    fn test_no_legal_move() {
+        // The first player is starved : the game is over and lost.
+        let mut b = Board::new();
+        b.red_holes = [0; 16];
+        b.blue_holes = [0; 16];
+        b.red_holes[1] = 10;
+        b.red_holes[3] = 4;
+        b.players_attics = [20, 20];
+        b.refresh_hash();
+        assert!(b.legal_moves(0).is_empty());
+
+        let result = decision_minmax(&b, 0, DEFAULT_TIME_BUDGET);
+        assert_eq!(None, result.best_move);
+        assert!(result.score < -WIN_THRESHOLD);
+        assert_eq!(0, result.depth);
+        assert!(result.pv.is_empty() && result.root_scores.is_empty());
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let b = Board::new();
+        let searcher = Searcher::new(SearchConfig { threads: 2, hash_mb: 1, ..SearchConfig::default() });
+
+        let now = Instant::now();
+        let mv = searcher.search(&b, 0, &SearchLimits::movetime(Duration::from_millis(50))).best_move.unwrap();
+        assert!(now.elapsed() < Duration::from_millis(500));
+        assert!(b.is_this_move_possible(0, mv.hole, mv.color));
+
+        let result = searcher.search(&b, 0, &SearchLimits::movetime(Duration::ZERO));
+        assert!(result.best_move.is_some_and(|mv| b.is_this_move_possible(0, mv.hole, mv.color)));
+        assert_eq!(0, result.depth);
+        assert_eq!(vec![result.best_move.unwrap()], result.pv);
+        assert!(result.root_scores.is_empty());
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let b = Board::new();
+        let searcher = Searcher::new(SearchConfig { threads: 2, hash_mb: 1, ..SearchConfig::default() });
+
+        assert_eq!(3, searcher.search(&b, 0, &SearchLimits::depth(3)).depth);
+
+        // Checked every thousand nodes or so, by each thread.
+        let result = searcher.search(&b, 0, &SearchLimits::nodes(5_000));
+        assert!(result.nodes < 5_000 + 2 * 2 * NODES_BETWEEN_CLOCK_CHECKS);
+        assert!(result.depth > 0 && result.depth < 10);
+
+        let now = Instant::now();
+        let clock = Clock::new(Duration::from_millis(300), Duration::ZERO);
+        let result = searcher.search(&b, 0, &SearchLimits::clock(clock));
+        assert!(now.elapsed() < Duration::from_millis(300));
+        assert!(result.best_move.is_some_and(|mv| b.is_this_move_possible(0, mv.hole, mv.color)));
+
+        // The first limit reached stops the search.
+        let limits = SearchLimits { depth: Some(2), ..SearchLimits::movetime(Duration::from_secs(60)) };
+        assert_eq!(2, searcher.search(&b, 0, &limits).depth);
+    }
+
+    #[test]
+    fn test_thread_amount() {
+        let mut b = Board::new();
+        b.play_move(0, 0, Color::Red);
+
+        for threads in [1, 3, 32] {
+            let mut searcher = Searcher::new(SearchConfig { threads: 1, hash_mb: 1, ..SearchConfig::default() });
+            searcher.set_threads(threads);
+            assert_eq!(threads, searcher.config().threads);
+
+            let mv = searcher.search(&b, 1, &SearchLimits::movetime(Duration::from_millis(50))).best_move.unwrap();
+            assert!(b.is_this_move_possible(1, mv.hole, mv.color));
+        }
+    }
+
+    #[test]
+    fn test_search_observer() {
+        let mut b = Board::new();
+        b.play_move(0, 0, Color::Red);
+
+        for mode in [ParallelMode::RootSplit, ParallelMode::LazySmp] {
+            let searcher = Searcher::new(SearchConfig { threads: 2, hash_mb: 1, mode });
+            let mut infos = Vec::new();
+            let mut observer = |info: &SearchInfo| infos.push((info.depth, info.score, info.pv.to_vec(), info.nodes));
+            let result = searcher.search_with_observer(&b, 1, &SearchLimits::depth(5), &mut observer);
+
+            assert_eq!(vec![1, 2, 3, 4, 5], infos.iter().map(|info| info.0).collect::<Vec<i32>>());
+            assert!(infos.windows(2).all(|pair| pair[0].3 <= pair[1].3));
+            let (_, score, pv, _) = infos.last().unwrap();
+            assert_eq!(result.score, *score);
+            assert_eq!(result.pv, *pv);
+        }
+    }
+
+    #[test]
+    fn test_stop_handle() {
+        let b = Board::new();
+        for mode in [ParallelMode::RootSplit, ParallelMode::LazySmp] {
+            let searcher = Searcher::new(SearchConfig { threads: 2, hash_mb: 1, mode });
+            let stop = StopHandle::new();
+
+            let now = Instant::now();
+            let result = crossbeam::scope(|scope| {
+                let handle = stop.clone();
+                scope.spawn(move |_| {
+                    std::thread::sleep(Duration::from_millis(100));
+                    handle.stop();
+                });
+                searcher.search_with_stop(&b, 0, &SearchLimits::infinite(), &stop, &mut ())
+            }).unwrap();
+
+            assert!(now.elapsed() < Duration::from_secs(5));
+            assert!(result.depth > 0);
+            assert!(result.best_move.is_some_and(|mv| b.is_this_move_possible(0, mv.hole, mv.color)));
+        }
+
+        // Stopped before it starts : no depth, but still a move.
+        let stop = StopHandle::new();
+        stop.stop();
+        let result = Searcher::new(SearchConfig { threads: 1, hash_mb: 1, ..SearchConfig::default() })
+            .search_with_stop(&b, 0, &SearchLimits::infinite(), &stop, &mut ());
+        assert_eq!(0, result.depth);
+        assert!(result.best_move.is_some_and(|mv| b.is_this_move_possible(0, mv.hole, mv.color)));
+    }
+
+    #[test]
+    fn test_lazy_smp() {
+        let mut b = Board::new();
+        b.play_move(0, 0, Color::Red);
+
+        let config = SearchConfig { threads: 1, hash_mb: 1, ..SearchConfig::default() };
+        let root_split = Searcher::new(config).search(&b, 1, &SearchLimits::depth(5));
+        let lazy_smp = Searcher::new(SearchConfig { mode: ParallelMode::LazySmp, ..config })
+            .search(&b, 1, &SearchLimits::depth(5));
+        assert_eq!(5, root_split.depth);
+        assert_eq!(5, lazy_smp.depth);
+        assert_eq!(root_split.best_move, lazy_smp.best_move);
+
+        let searcher = Searcher::new(SearchConfig { threads: 4, mode: ParallelMode::LazySmp, ..config });
+        let stats = searcher.search(&b, 1, &SearchLimits::depth(5));
+        assert_eq!(5, stats.depth);
+        assert!(stats.best_move.is_some_and(|mv| b.is_this_move_possible(1, mv.hole, mv.color)));
+
+        let mv = searcher.search(&b, 1, &SearchLimits::movetime(Duration::from_millis(50))).best_move.unwrap();
+        assert!(b.is_this_move_possible(1, mv.hole, mv.color));
+    }
+
+    #[test]
+    fn test_principal_variation() {
+        let mut b = Board::new();
+        b.play_move(0, 0, Color::Red);
+
+        for mode in [ParallelMode::RootSplit, ParallelMode::LazySmp] {
+            let searcher = Searcher::new(SearchConfig { threads: 2, hash_mb: 1, mode });
+            let result = searcher.search(&b, 1, &SearchLimits::depth(6));
+            assert_eq!(6, result.depth);
+            assert_eq!(b.legal_moves(1).len(), result.root_scores.len());
+            assert_eq!((result.best_move.unwrap(), result.score), result.root_scores[0]);
+            assert!(result.root_scores.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+            assert_eq!(result.best_move, result.pv.first().copied());
+            assert!(result.pv.len() <= 7);
+
+            let mut line_board = b;
+            let mut player = 1;
+            for &mv in &result.pv {
+                assert_eq!(Ok(()), line_board.try_play_move(player, mv).map(|_| ()));
+                player = (player + 1) % 2;
             }
-        );
+        }
     }
-}
+
+    /// Time to reach a fixed depth and nodes per second of both parallel modes,
+    /// from the opening to the endgame :
+    /// `cargo test --release bench_parallel_modes -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_parallel_modes() {
+        const BENCH_DEPTH: i32 = 8;
+
+        let mut positions = Vec::new();
+        for plies in [0, 10, 20, 30, 40] {
+            let mut b = Board::new();
+            let moves = random_moves(0x5eed, plies);
+            for (ply, &mv) in moves.iter().enumerate() {
+                b.make_move(ply % 2, mv);
+            }
+            if !b.is_final_position() {
+                positions.push((b, moves.len() % 2));
+            }
+        }
+
+        let mut thread_amounts = vec![1, 2, 4, available_cores()];
+        thread_amounts.sort();
+        thread_amounts.dedup();
+
+        println!("{} positions searched to depth {}", positions.len(), BENCH_DEPTH);
+        println!("{:<10} {:>7} {:>14} {:>12}", "mode", "threads", "time to depth", "nodes/s");
+        for mode in [ParallelMode::RootSplit, ParallelMode::LazySmp] {
+            for &threads in &thread_amounts {
+                let searcher = Searcher::new(SearchConfig { threads, hash_mb: 64, mode });
+                let mut elapsed = Duration::ZERO;
+                let mut nodes = 0;
+                for (b, player) in &positions {
+                    searcher.clear();
+                    let stats = searcher.search(b, *player, &SearchLimits::depth(BENCH_DEPTH));
+                    elapsed += stats.elapsed;
+                    nodes += stats.nodes;
+                }
+                println!(
+                    "{:<10} {:>7} {:>13.3}s {:>12.0}",
+                    format!("{:?}", mode),
+                    threads,
+                    elapsed.as_secs_f64(),
+                    nodes as f64 / elapsed.as_secs_f64()
+                );
+            }
+        }
+    }
+}
\ No newline at end of file
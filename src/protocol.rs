@@ -0,0 +1,442 @@
+use crate::board::{Board, Move, MoveError, NotationError};
+use crate::eval::{DefaultEvaluator, EvalWeights, TERM_NAMES};
+use crate::limits::{Clock, SearchLimits};
+use crate::minmax::{format_line, SearchConfig, SearchInfo, Searcher, StopHandle};
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A line sent to the engine, in the spirit of UCI :
+///
+/// - `newgame`
+/// - `position startpos [moves 3R 12B ...]`, or `position notation HOLES ATTICS PLAYER [moves ...]`
+///   with the notation of `Board::to_notation`
+/// - `go [depth N] [nodes N] [movetime MS] [time1 MS] [time2 MS] [inc1 MS] [inc2 MS] [movestogo N] [infinite]`
+/// - `stop`
+/// - `setoption name NAME value VALUE`
+/// - `isready`
+/// - `quit`
+///
+/// The engine answers `info depth D score S nodes N nps N time MS pv ...`
+/// after each depth, `bestmove MOVE [ponder MOVE]` at the end of a search,
+/// `readyok` and `info string MESSAGE` for the errors.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Command {
+    NewGame,
+    /// Moves from the `start` position.
+    Position { start: Board, moves: Vec<Move> },
+    /// The clocks are the ones of both players, the engine taking the one of
+    /// the player to move. Without any limit, searches until `stop`.
+    Go { limits: SearchLimits, clocks: [Option<Clock>; 2] },
+    Stop,
+    SetOption { name: String, value: String },
+    IsReady,
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, ProtocolError> {
+        let mut tokens = line.split_whitespace();
+        let command = match tokens.next() {
+            Some("newgame") => Command::NewGame,
+            Some("position") => parse_position(&mut tokens)?,
+            Some("go") => parse_go(&mut tokens)?,
+            Some("stop") => Command::Stop,
+            Some("setoption") => parse_setoption(&mut tokens)?,
+            Some("isready") => Command::IsReady,
+            Some("quit") => Command::Quit,
+            Some(other) => return Err(ProtocolError::UnknownCommand(other.to_string())),
+            None => return Err(ProtocolError::UnknownCommand(String::new())),
+        };
+        if let Some(extra) = tokens.next() {
+            return Err(ProtocolError::Syntax(extra.to_string()));
+        }
+        return Ok(command);
+    }
+}
+
+fn parse_position<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Command, ProtocolError> {
+    let start = match tokens.next() {
+        Some("startpos") => Board::new(),
+        Some("notation") => {
+            let notation = tokens.take(3).collect::<Vec<&str>>().join(" ");
+            Board::from_notation(&notation).map_err(ProtocolError::Notation)?
+        }
+        Some(other) => return Err(ProtocolError::Syntax(other.to_string())),
+        None => return Err(ProtocolError::Syntax(String::new())),
+    };
+    let mut moves = Vec::new();
+    match tokens.next() {
+        Some("moves") => {
+            for mv in tokens {
+                moves.push(mv.parse::<Move>().map_err(|_| ProtocolError::Syntax(mv.to_string()))?);
+            }
+        }
+        Some(other) => return Err(ProtocolError::Syntax(other.to_string())),
+        None => {}
+    }
+    return Ok(Command::Position { start, moves });
+}
+
+fn parse_go<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Command, ProtocolError> {
+    let mut limits = SearchLimits::infinite();
+    let mut times = [None; 2];
+    let mut increments = [Duration::ZERO; 2];
+    let mut moves_to_go = None;
+    while let Some(token) = tokens.next() {
+        if token == "infinite" {
+            continue;
+        }
+        let value = tokens.next().ok_or(ProtocolError::Syntax(String::new()))?;
+        let invalid = || ProtocolError::InvalidValue { name: token.to_string(), value: value.to_string() };
+        let millis = || value.parse().map(Duration::from_millis).map_err(|_| invalid());
+        match token {
+            "depth" => limits.depth = Some(value.parse().map_err(|_| invalid())?),
+            "nodes" => limits.nodes = Some(value.parse().map_err(|_| invalid())?),
+            "movetime" => limits.movetime = Some(millis()?),
+            "time1" => times[0] = Some(millis()?),
+            "time2" => times[1] = Some(millis()?),
+            "inc1" => increments[0] = millis()?,
+            "inc2" => increments[1] = millis()?,
+            "movestogo" => moves_to_go = Some(value.parse().map_err(|_| invalid())?),
+            other => return Err(ProtocolError::Syntax(other.to_string())),
+        }
+    }
+    let clocks = [0, 1].map(|player| times[player].map(|remaining| Clock { moves_to_go, ..Clock::new(remaining, increments[player]) }));
+    return Ok(Command::Go { limits, clocks });
+}
+
+fn parse_setoption<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Command, ProtocolError> {
+    if tokens.next() != Some("name") {
+        return Err(ProtocolError::Syntax("setoption".to_string()));
+    }
+    let mut name = Vec::new();
+    for token in tokens.by_ref() {
+        if token == "value" {
+            let value = tokens.collect::<Vec<&str>>().join(" ");
+            return Ok(Command::SetOption { name: name.join(" "), value });
+        }
+        name.push(token);
+    }
+    return Err(ProtocolError::Syntax(name.join(" ")));
+}
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    UnknownCommand(String),
+    /// Unexpected word in a command.
+    Syntax(String),
+    IllegalMove(MoveError),
+    Notation(NotationError),
+    UnknownOption(String),
+    InvalidValue { name: String, value: String },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::UnknownCommand(command) => write!(f, "unknown command \"{}\"", command),
+            ProtocolError::Syntax(text) => write!(f, "unexpected \"{}\"", text),
+            ProtocolError::IllegalMove(error) => write!(f, "illegal move : {}", error),
+            ProtocolError::Notation(error) => write!(f, "invalid position : {}", error),
+            ProtocolError::UnknownOption(name) => write!(f, "unknown option \"{}\"", name),
+            ProtocolError::InvalidValue { name, value } => write!(f, "invalid value for {} : {}", name, value)
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError { }
+
+/// The engine behind the protocol. `go` searches in another thread, so
+/// that `stop`, `isready` and `quit` are answered meanwhile ; the other
+/// commands stop the search in progress, which still sends its best move.
+pub struct ProtocolEngine<W: Write + Send + 'static> {
+    output: Arc<Mutex<W>>,
+    board: Board,
+    player: usize,
+    weights: EvalWeights,
+    /// `None` while a search owns it.
+    searcher: Option<Searcher>,
+    search: Option<(thread::JoinHandle<Searcher>, StopHandle)>,
+}
+
+impl<W: Write + Send + 'static> ProtocolEngine<W> {
+    pub fn new(output: W) -> ProtocolEngine<W> {
+        ProtocolEngine {
+            output: Arc::new(Mutex::new(output)),
+            board: Board::new(),
+            player: 0,
+            weights: EvalWeights::default(),
+            searcher: Some(Searcher::new(SearchConfig::default())),
+            search: None,
+        }
+    }
+
+    /// Runs the command of `line`, errors being sent as `info string`.
+    /// Returns false once the engine has to quit.
+    pub fn handle_line(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+        return match Command::parse(line).and_then(|command| self.execute(command)) {
+            Ok(go_on) => go_on,
+            Err(error) => {
+                send(&self.output, &format!("info string {}", error));
+                true
+            }
+        };
+    }
+
+    pub fn execute(&mut self, command: Command) -> Result<bool, ProtocolError> {
+        match command {
+            Command::IsReady => send(&self.output, "readyok"),
+            Command::Stop => self.stop(),
+            Command::Quit => {
+                self.stop();
+                return Ok(false);
+            }
+            Command::NewGame => {
+                self.stop();
+                self.board = Board::new();
+                self.player = 0;
+                self.searcher().clear();
+            }
+            Command::Position { start, moves } => {
+                self.stop();
+                let mut board = start;
+                let mut player = start.side_to_move;
+                for mv in moves {
+                    board.try_play_move(player, mv).map_err(ProtocolError::IllegalMove)?;
+                    player = (player + 1) % 2;
+                }
+                self.board = board;
+                self.player = player;
+            }
+            Command::SetOption { name, value } => {
+                self.stop();
+                self.set_option(&name, &value)?;
+            }
+            Command::Go { mut limits, clocks } => {
+                self.stop();
+                limits.clock = clocks[self.player];
+                self.go(limits);
+            }
+        }
+        return Ok(true);
+    }
+
+    fn searcher(&mut self) -> &mut Searcher {
+        return self.searcher.as_mut().expect("the searcher is back once the search ended");
+    }
+
+    /// Options : `threads`, `hash` (megabytes), `mode` (`root-split` or
+    /// `lazy-smp`), `weights` (a file) and every term of the `EvalWeights`.
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), ProtocolError> {
+        let invalid = || ProtocolError::InvalidValue { name: name.to_string(), value: value.to_string() };
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "threads" => self.searcher().set_threads(value.parse().map_err(|_| invalid())?),
+            "hash" => self.searcher().set_hash_size(value.parse().map_err(|_| invalid())?),
+            "mode" => {
+                let mode = value.parse().map_err(|_| invalid())?;
+                let config = SearchConfig { mode, ..*self.searcher().config() };
+                self.searcher = Some(Searcher::with_evaluator(config, DefaultEvaluator::new(self.weights)));
+            }
+            _ => {
+                if name == "weights" {
+                    self.weights = EvalWeights::load(Path::new(value)).map_err(|_| invalid())?;
+                } else if TERM_NAMES.contains(&name.as_str()) {
+                    self.weights.set_from_str(&format!("{}={}", name, value)).map_err(|_| invalid())?;
+                } else {
+                    return Err(ProtocolError::UnknownOption(name));
+                }
+                let config = *self.searcher().config();
+                self.searcher = Some(Searcher::with_evaluator(config, DefaultEvaluator::new(self.weights)));
+            }
+        }
+        return Ok(());
+    }
+
+    fn go(&mut self, limits: SearchLimits) {
+        let searcher = self.searcher.take().expect("the searcher is back once the search ended");
+        let (board, player) = (self.board, self.player);
+        let stop = StopHandle::new();
+        let search_stop = stop.clone();
+        let output = Arc::clone(&self.output);
+        let handle = thread::spawn(move || {
+            let result = searcher.search_with_stop(&board, player, &limits, &search_stop, &mut |info: &SearchInfo| {
+                let line = format!(
+                    "info depth {} score {} nodes {} nps {} time {} pv {}",
+                    info.depth,
+                    info.score,
+                    info.nodes,
+                    info.nps,
+                    info.elapsed.as_millis(),
+                    format_line(info.pv)
+                );
+                send(&output, &line);
+            });
+            match (result.best_move, result.pv.get(1)) {
+                (Some(mv), Some(reply)) => send(&output, &format!("bestmove {} ponder {}", mv, reply)),
+                (Some(mv), None) => send(&output, &format!("bestmove {}", mv)),
+                (None, _) => send(&output, "bestmove none"),
+            }
+            searcher
+        });
+        self.search = Some((handle, stop));
+    }
+
+    /// Ends the search in progress, if any, once it sent its best move.
+    fn stop(&mut self) {
+        if let Some((_, stop)) = &self.search {
+            stop.stop();
+        }
+        self.wait();
+    }
+
+    /// Waits for the search in progress, if any.
+    pub fn wait(&mut self) {
+        if let Some((handle, _)) = self.search.take() {
+            self.searcher = Some(handle.join().unwrap());
+        }
+    }
+
+    /// Waits for the search in progress, then gives the output back.
+    pub fn into_output(mut self) -> W {
+        self.wait();
+        return Arc::try_unwrap(self.output).ok().expect("the search thread is over").into_inner().unwrap();
+    }
+}
+
+fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+    // Nobody is left to tell if the other side closed the pipe.
+    let _ = writeln!(output, "{}", line).and_then(|_| output.flush());
+}
+
+/// Reads commands from `input` until `quit`. At the end of the input, the
+/// search in progress still goes on to its best move.
+pub fn run_protocol(input: impl BufRead, output: impl Write + Send + 'static) -> io::Result<()> {
+    let mut engine = ProtocolEngine::new(output);
+    for line in input.lines() {
+        if !engine.handle_line(&line?) {
+            return Ok(());
+        }
+    }
+    engine.wait();
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(Command::NewGame, Command::parse("newgame").unwrap());
+        assert_eq!(Command::Position { start: Board::new(), moves: Vec::new() }, Command::parse("position startpos").unwrap());
+        assert_eq!(
+            Command::Position { start: Board::new(), moves: vec![Move::new(2, Color::Red), Move::new(11, Color::Blue)] },
+            Command::parse("position startpos moves 3R 12B").unwrap()
+        );
+        let mut start = Board::new();
+        start.play_move(0, 2, Color::Red);
+        assert_eq!(
+            Command::Position { start, moves: vec![Move::new(11, Color::Blue)] },
+            Command::parse(&format!("position notation {} moves 12B", start.to_notation())).unwrap()
+        );
+        assert_eq!(Command::Go { limits: SearchLimits::movetime(Duration::from_millis(500)), clocks: [None; 2] }, Command::parse("go movetime 500").unwrap());
+        assert_eq!(Command::Go { limits: SearchLimits::infinite(), clocks: [None; 2] }, Command::parse("go infinite").unwrap());
+        let clock = Clock { moves_to_go: Some(20), ..Clock::new(Duration::from_secs(60), Duration::from_secs(1)) };
+        assert_eq!(
+            Command::Go { limits: SearchLimits::depth(8), clocks: [Some(clock), None] },
+            Command::parse("go depth 8 time1 60000 inc1 1000 movestogo 20").unwrap()
+        );
+        assert_eq!(
+            Command::SetOption { name: "Threads".to_string(), value: "2".to_string() },
+            Command::parse("setoption name Threads value 2").unwrap()
+        );
+
+        assert!(matches!(Command::parse("go"), Ok(Command::Go { .. })));
+        assert!(matches!(Command::parse("play 3R"), Err(ProtocolError::UnknownCommand(_))));
+        assert!(matches!(Command::parse("position startpos moves 3X"), Err(ProtocolError::Syntax(_))));
+        assert!(matches!(Command::parse("position notation 2,2 0,0 1"), Err(ProtocolError::Notation(_))));
+        assert!(matches!(Command::parse("go depth x"), Err(ProtocolError::InvalidValue { .. })));
+        assert!(matches!(Command::parse("setoption name Threads"), Err(ProtocolError::Syntax(_))));
+        assert!(matches!(Command::parse("stop now"), Err(ProtocolError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_protocol_engine() {
+        let mut engine = ProtocolEngine::new(Vec::new());
+        for line in [
+            "isready",
+            "setoption name threads value 1",
+            "setoption name Contempt value x",
+            "setoption name colour value red",
+            "position startpos moves 1R 3R",
+            "position startpos moves 1R 14B 11B 8B 5B 4R",
+            "go depth 6",
+        ] {
+            assert!(engine.handle_line(line));
+        }
+        engine.wait();
+        for line in ["newgame", "go infinite", "stop"] {
+            assert!(engine.handle_line(line));
+        }
+        assert!(!engine.handle_line("quit"));
+        let output = String::from_utf8(engine.into_output()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!("readyok", lines[0]);
+        assert_eq!("info string invalid value for Contempt : x", lines[1]);
+        assert_eq!("info string unknown option \"colour\"", lines[2]);
+        assert!(lines[3].starts_with("info string illegal move"));
+        assert!(lines[4].starts_with("info depth 1 score"));
+        // The known result of this position, see the search tests.
+        assert!(lines[9].starts_with("info depth 6 score 57 ") && lines[9].contains(" pv 15B"), "{}", lines[9]);
+        assert!(lines[10].starts_with("bestmove 15B ponder "));
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_command_during_search() {
+        // The new position stops the infinite search instead of waiting for it.
+        let mut engine = ProtocolEngine::new(Vec::new());
+        for line in ["go infinite", "position startpos moves 1R", "stop", "isready"] {
+            assert!(engine.handle_line(line));
+        }
+        let output = String::from_utf8(engine.into_output()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[lines.len() - 2].starts_with("bestmove "));
+        assert_eq!(Some(&"readyok"), lines.last());
+    }
+
+    #[test]
+    fn test_run_protocol() {
+        // At the end of the input, the search goes on to its end.
+        let input = "position startpos\ngo depth 3\n";
+        let output = Arc::new(Mutex::new(Vec::new()));
+        run_protocol(input.as_bytes(), SharedOutput(Arc::clone(&output))).unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("info depth 3 "));
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            return self.0.lock().unwrap().write(buf);
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::board::{Board, Move};
+use crate::dataset::{format_result, LabeledPosition};
+use crate::selfplay::game_result;
+use crossbeam::channel;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time an engine has to answer `isready` when a game starts.
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct RefereeConfig {
+    /// Thinking time of every move, sent with `go movetime`.
+    pub move_time: Duration,
+    /// Allowed on top of `move_time` for the communication : an answer later
+    /// than both loses the game.
+    pub margin: Duration,
+    /// See `Termination::MaxPlies`.
+    pub max_plies: usize,
+}
+
+impl Default for RefereeConfig {
+    fn default() -> RefereeConfig {
+        RefereeConfig { move_time: Duration::from_millis(1000), margin: Duration::from_millis(200), max_plies: 300 }
+    }
+}
+
+/// A player of a refereed game, asked its moves.
+pub trait Contestant {
+    fn new_game(&mut self) -> io::Result<()>;
+
+    /// The answer to the position reached by `moves` from the start, `None`
+    /// when it did not come within the time of `config`.
+    fn request_move(&mut self, moves: &[Move], config: &RefereeConfig) -> io::Result<Option<String>>;
+}
+
+/// An engine executable speaking the text protocol of `oware protocol` on
+/// its standard input and output.
+pub struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines of the standard output, read by another thread so that the
+    /// referee can stop waiting.
+    lines: channel::Receiver<String>,
+}
+
+impl EngineProcess {
+    /// `command` is the executable followed by its arguments, separated by
+    /// spaces.
+    pub fn spawn(command: &str) -> io::Result<EngineProcess> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or(io::Error::new(io::ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut child = Command::new(program).args(words).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().expect("the input is piped");
+        let stdout = child.stdout.take().expect("the output is piped");
+
+        let (sender, lines) = channel::unbounded();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        return Ok(EngineProcess { child, stdin, lines });
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        return self.stdin.flush();
+    }
+
+    /// The first line starting with the word `keyword`, `None` once
+    /// `deadline` is over.
+    fn wait_for(&self, keyword: &str, deadline: Instant) -> io::Result<Option<String>> {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(timeout) {
+                Ok(line) if line.split_whitespace().next() == Some(keyword) => return Ok(Some(line)),
+                Ok(_) => {}
+                Err(channel::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(channel::RecvTimeoutError::Disconnected) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+}
+
+impl Contestant for EngineProcess {
+    fn new_game(&mut self) -> io::Result<()> {
+        self.send("newgame")?;
+        self.send("isready")?;
+        return match self.wait_for("readyok", Instant::now() + READY_TIMEOUT)? {
+            Some(_) => Ok(()),
+            None => Err(io::Error::new(io::ErrorKind::TimedOut, "no readyok")),
+        };
+    }
+
+    fn request_move(&mut self, moves: &[Move], config: &RefereeConfig) -> io::Result<Option<String>> {
+        let mut position = String::from("position startpos moves");
+        for mv in moves {
+            position.push_str(&format!(" {}", mv));
+        }
+        self.send(&position)?;
+        self.send(&format!("go movetime {}", config.move_time.as_millis()))?;
+
+        let answer = self.wait_for("bestmove", Instant::now() + config.move_time + config.margin)?;
+        if answer.is_none() {
+            // Too late anyway, the engine has not to go on.
+            self.send("stop")?;
+        }
+        return Ok(answer.map(|line| line.split_whitespace().nth(1).unwrap_or_default().to_string()));
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Why a refereed game ended.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Termination {
+    /// Won or drawn by the rules, see `Board::check_win`.
+    Rules,
+    /// Drawn after `RefereeConfig::max_plies`.
+    MaxPlies,
+    /// The player's answer was not a legal move : it loses.
+    IllegalMove { player: usize, answer: String },
+    /// The player did not answer in time : it loses.
+    Timeout { player: usize },
+    /// The player could not be talked to : it loses.
+    Disconnected { player: usize },
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Termination::Rules => write!(f, "end of the game"),
+            Termination::MaxPlies => write!(f, "too long, draw"),
+            Termination::IllegalMove { player, answer } => write!(f, "illegal move \"{}\" from player {}", answer, player + 1),
+            Termination::Timeout { player } => write!(f, "player {} lost on time", player + 1),
+            Termination::Disconnected { player } => write!(f, "player {} disconnected", player + 1)
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct RefereedGame {
+    pub moves: Vec<Move>,
+    /// See `LabeledPosition::result`.
+    pub result: f64,
+    pub termination: Termination,
+    /// Thinking time of every move, as measured by the referee.
+    pub times: Vec<Duration>,
+}
+
+/// The game record : the termination as a comment, then the result and the
+/// moves as in a dataset.
+impl fmt::Display for RefereedGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {} : {}", format_result(self.result), self.termination)?;
+        let position = LabeledPosition { moves: self.moves.clone(), result: self.result, score: None };
+        writeln!(f, "{}", position)
+    }
+}
+
+/// Plays a game between both contestants, the first one moving first, and
+/// checks every answer : a contestant which plays an illegal move, answers
+/// late or not at all loses.
+pub fn referee_game(mut contestants: [&mut dyn Contestant; 2], config: &RefereeConfig) -> RefereedGame {
+    let mut board = Board::new();
+    let mut moves = Vec::new();
+    let mut times = Vec::new();
+    let forfeit = |player: usize| if player == 0 { 0.0 } else { 1.0 };
+
+    for (player, contestant) in contestants.iter_mut().enumerate() {
+        if contestant.new_game().is_err() {
+            return RefereedGame { moves, result: forfeit(player), termination: Termination::Disconnected { player }, times };
+        }
+    }
+
+    let mut player = 0;
+    while board.check_win(true) == 3 {
+        if moves.len() >= config.max_plies {
+            return RefereedGame { moves, result: 0.5, termination: Termination::MaxPlies, times };
+        }
+
+        let start = Instant::now();
+        let answer = contestants[player].request_move(&moves, config);
+        let elapsed = start.elapsed();
+        let answer = match answer {
+            Ok(Some(answer)) if elapsed <= config.move_time + config.margin => answer,
+            Ok(_) => return RefereedGame { moves, result: forfeit(player), termination: Termination::Timeout { player }, times },
+            Err(_) => return RefereedGame { moves, result: forfeit(player), termination: Termination::Disconnected { player }, times },
+        };
+
+        let mv = match answer.parse::<Move>() {
+            Ok(mv) if board.is_this_move_possible(player, mv.hole, mv.color) => mv,
+            _ => {
+                let termination = Termination::IllegalMove { player, answer };
+                return RefereedGame { moves, result: forfeit(player), termination, times };
+            }
+        };
+        board.play_move(player, mv.hole, mv.color);
+        moves.push(mv);
+        times.push(elapsed);
+        player = (player + 1) % 2;
+    }
+
+    return RefereedGame { moves, result: game_result(&board), termination: Termination::Rules, times };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
+
+    /// Plays its first legal move, or answers `answer` at ply `at`.
+    struct Scripted {
+        at: usize,
+        answer: Option<String>,
+    }
+
+    impl Contestant for Scripted {
+        fn new_game(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+
+        fn request_move(&mut self, moves: &[Move], _config: &RefereeConfig) -> io::Result<Option<String>> {
+            if moves.len() == self.at {
+                return Ok(self.answer.clone());
+            }
+            let mut board = Board::new();
+            for (ply, &mv) in moves.iter().enumerate() {
+                board.make_move(ply % 2, mv);
+            }
+            return Ok(Some(board.legal_moves(moves.len() % 2)[0].to_string()));
+        }
+    }
+
+    fn scripted(at: usize, answer: Option<&str>) -> Scripted {
+        return Scripted { at, answer: answer.map(str::to_string) };
+    }
+
+    #[test]
+    fn test_referee_game() {
+        let config = RefereeConfig::default();
+        let game = referee_game([&mut scripted(usize::MAX, None), &mut scripted(usize::MAX, None)], &config);
+        assert_eq!(game.moves.len(), game.times.len());
+        let mut board = Board::new();
+        for (ply, &mv) in game.moves.iter().enumerate() {
+            board.try_play_move(ply % 2, mv).unwrap();
+        }
+        match game.termination {
+            Termination::Rules => assert_eq!(game_result(&board), game.result),
+            Termination::MaxPlies => assert_eq!(config.max_plies, game.moves.len()),
+            other => panic!("unexpected {}", other),
+        }
+
+        // 2R belongs to the second player.
+        let game = referee_game([&mut scripted(0, Some("2R")), &mut scripted(usize::MAX, None)], &config);
+        assert_eq!(Termination::IllegalMove { player: 0, answer: "2R".to_string() }, game.termination);
+        assert_eq!(0.0, game.result);
+        let game = referee_game([&mut scripted(0, Some("3R")), &mut scripted(1, Some("hello"))], &config);
+        assert_eq!(vec![Move::new(2, Color::Red)], game.moves);
+        assert_eq!(Termination::IllegalMove { player: 1, answer: "hello".to_string() }, game.termination);
+        assert_eq!(1.0, game.result);
+
+        let game = referee_game([&mut scripted(usize::MAX, None), &mut scripted(3, None)], &config);
+        assert_eq!(Termination::Timeout { player: 1 }, game.termination);
+        assert_eq!(3, game.moves.len());
+        assert_eq!("# 1-0 : player 2 lost on time\n1-0 | 1R 2R 3R\n", game.to_string());
+    }
+}
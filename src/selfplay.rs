@@ -0,0 +1,204 @@
+use crate::board::{random_moves, Board, Move};
+use crate::dataset::LabeledPosition;
+use crate::eval::{DefaultEvaluator, EvalWeights};
+use crate::limits::SearchLimits;
+use crate::minmax::{available_cores, SearchConfig, Searcher, DEFAULT_HASH_MB};
+use crossbeam::channel;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub struct SelfPlayConfig {
+    pub games: usize,
+    /// Games played at the same time, with one search thread each.
+    pub parallel: usize,
+    pub limits: SearchLimits,
+    /// Transposition table of each game, in megabytes. It is cleared before
+    /// every search, so that each score is the one of a fresh search.
+    pub hash_mb: usize,
+    pub weights: EvalWeights,
+    /// Random moves opening every game, so that the games differ.
+    pub random_plies: usize,
+    /// Games are stopped after this many plies, see `game_result`.
+    pub max_plies: usize,
+    /// The openings only depend on the seed and the game number.
+    pub seed: u64,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> SelfPlayConfig {
+        SelfPlayConfig {
+            games: 100,
+            parallel: available_cores(),
+            limits: SearchLimits::depth(4),
+            hash_mb: DEFAULT_HASH_MB,
+            weights: EvalWeights::default(),
+            random_plies: 6,
+            max_plies: 300,
+            seed: 1,
+        }
+    }
+}
+
+/// A finished game between two copies of the engine.
+#[derive(PartialEq, Debug)]
+pub struct GameRecord {
+    /// Number of the game, from 0.
+    pub index: usize,
+    pub moves: Vec<Move>,
+    /// Score of the search before each move, seen by the player to move,
+    /// `None` for the random opening moves.
+    pub scores: Vec<Option<i32>>,
+    /// See `LabeledPosition::result`.
+    pub result: f64,
+}
+
+impl GameRecord {
+    /// Every position searched during the game, labeled with its result.
+    pub fn positions(&self) -> Vec<LabeledPosition> {
+        return self
+            .scores
+            .iter()
+            .enumerate()
+            .filter(|(_, score)| score.is_some())
+            .map(|(ply, &score)| LabeledPosition { moves: self.moves[..ply].to_vec(), result: self.result, score })
+            .collect();
+    }
+}
+
+/// Result of the game stopped in `board`, see `LabeledPosition::result` : a
+/// game stopped before its end is a draw.
+pub fn game_result(board: &Board) -> f64 {
+    if !board.is_final_position() {
+        return 0.5;
+    }
+    return match board.check_win(true) {
+        0 => 1.0,
+        1 => 0.0,
+        _ => 0.5,
+    };
+}
+
+/// Plays game number `index` with `searcher` for both players.
+pub fn play_game(searcher: &Searcher, config: &SelfPlayConfig, index: usize) -> GameRecord {
+    let mut board = Board::new();
+    let mut player = 0;
+    let mut moves = random_moves(config.seed.wrapping_add(index as u64), config.random_plies.min(config.max_plies));
+    let mut scores = vec![None; moves.len()];
+    for &mv in &moves {
+        board.make_move(player, mv);
+        player = (player + 1) % 2;
+    }
+
+    while !board.is_final_position() && moves.len() < config.max_plies {
+        // What the table learned from the previous positions would change the
+        // scores written to the dataset.
+        searcher.clear();
+        let result = searcher.search(&board, player, &config.limits);
+        let mv = result.best_move.expect("an unfinished game has legal moves");
+        board.make_move(player, mv);
+        moves.push(mv);
+        scores.push(Some(result.score));
+        player = (player + 1) % 2;
+    }
+    return GameRecord { index, moves, scores, result: game_result(&board) };
+}
+
+/// Plays `config.games` games, `config.parallel` at a time. `on_game` is
+/// called by the calling thread with each game once it is over, in the
+/// order they finish.
+pub fn run_selfplay(config: &SelfPlayConfig, on_game: &mut dyn FnMut(GameRecord)) {
+    let new_searcher = || {
+        let search_config = SearchConfig { hash_mb: config.hash_mb, ..SearchConfig::deterministic() };
+        return Searcher::with_evaluator(search_config, DefaultEvaluator::new(config.weights));
+    };
+    play_games(
+        config.games,
+        config.parallel,
+        new_searcher,
+        |searcher, index| play_game(searcher, config, index),
+        &mut |game| {
+            on_game(game);
+            return true;
+        },
+    );
+}
+
+/// Plays games number `0..games` on `parallel` threads. Each thread makes
+/// its players once with `new_players`, then plays the games it takes with
+/// `play_game`. `on_game` is called by the calling thread with each game
+/// once it is over, in the order they finish ; once it returns false, no
+/// game starts and the games still running are dropped.
+pub fn play_games<P, G: Send>(
+    games: usize,
+    parallel: usize,
+    new_players: impl Fn() -> P + Sync,
+    play_game: impl Fn(&P, usize) -> G + Sync,
+    on_game: &mut dyn FnMut(G) -> bool,
+) {
+    let next_game = AtomicUsize::new(0);
+    let done = AtomicBool::new(false);
+    let (sender, receiver) = channel::unbounded();
+
+    crossbeam::scope(|scope| {
+        for _ in 0..parallel.clamp(1, games.max(1)) {
+            let (next_game, done) = (&next_game, &done);
+            let (new_players, play_game) = (&new_players, &play_game);
+            let sender = sender.clone();
+            scope.spawn(move |_| {
+                let players = new_players();
+                while !done.load(Ordering::Relaxed) {
+                    let index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if index >= games {
+                        break;
+                    }
+                    sender.send(play_game(&players, index)).unwrap();
+                }
+            });
+        }
+        drop(sender);
+
+        for game in receiver {
+            if !done.load(Ordering::Relaxed) && !on_game(game) {
+                done.store(true, Ordering::Relaxed);
+            }
+        }
+    }).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selfplay() {
+        let config = SelfPlayConfig { games: 4, parallel: 2, limits: SearchLimits::depth(2), hash_mb: 1, ..SelfPlayConfig::default() };
+        let mut games = Vec::new();
+        run_selfplay(&config, &mut |game| games.push(game));
+        games.sort_by_key(|game| game.index);
+
+        assert_eq!(vec![0, 1, 2, 3], games.iter().map(|game| game.index).collect::<Vec<usize>>());
+        for game in &games {
+            assert_eq!(game.moves.len(), game.scores.len());
+            assert!(game.scores[..config.random_plies].iter().all(|score| score.is_none()));
+
+            let positions = game.positions();
+            assert_eq!(game.moves.len() - config.random_plies, positions.len());
+            for position in &positions {
+                assert!(position.score.is_some());
+                assert_eq!(game.result, position.result);
+                assert!(position.board().is_ok());
+            }
+        }
+
+        // Different openings, but the same game for the same seed and number.
+        assert_ne!(games[0].moves[..config.random_plies], games[1].moves[..config.random_plies]);
+        let searcher = Searcher::new(SearchConfig { hash_mb: 1, ..SearchConfig::deterministic() });
+        assert_eq!(games[2], play_game(&searcher, &config, 2));
+
+        // Every score is the one of a fresh search of its position.
+        for position in games[1].positions() {
+            let (board, player) = position.board().unwrap();
+            let fresh = Searcher::new(SearchConfig { hash_mb: 1, ..SearchConfig::deterministic() });
+            assert_eq!(position.score, Some(fresh.search(&board, player, &config.limits).score));
+        }
+    }
+}
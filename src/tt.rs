@@ -0,0 +1,231 @@
+use crate::board::{Color, Move};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Kind of score stored for a position, from the point of view of the
+/// player the search is for.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    /// The position is worth at least the score (beta cutoff).
+    Lower,
+    /// The position is worth at most the score (no move raised alpha).
+    Upper
+}
+
+impl Copy for Bound { }
+
+impl Clone for Bound {
+    fn clone(&self) -> Bound {
+        *self
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct TtEntry {
+    /// Remaining depth the score was searched with.
+    pub depth: i32,
+    pub bound: Bound,
+    pub score: i32,
+    pub best_move: Option<Move>
+}
+
+/// An entry is two words : the data and the key xored with the data. A torn
+/// write from another thread never matches the key, so no lock is needed.
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64
+}
+
+/// Fixed-size hash table shared by all the search threads. The slots go by
+/// pairs : the first one keeps the deepest result of the current search, the
+/// second one the last result stored, so that the shallow entries near the
+/// root are not lost to the many leaves.
+pub struct TranspositionTable {
+    slots: Box<[Slot]>,
+    /// Index mask of the pairs.
+    mask: usize,
+    /// Searches started, modulo 64 : the deep entries of the previous ones
+    /// are replaced first.
+    generation: AtomicU64
+}
+
+impl TranspositionTable {
+    /// Allocates the largest power of two of entries fitting in `size_mb` megabytes.
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let bytes = size_mb.max(1) * 1024 * 1024;
+        let mut capacity = 2;
+        while capacity * 2 * std::mem::size_of::<Slot>() <= bytes {
+            capacity *= 2;
+        }
+
+        let slots = (0..capacity)
+            .map(|_| Slot { key: AtomicU64::new(0), data: AtomicU64::new(0) })
+            .collect::<Vec<Slot>>()
+            .into_boxed_slice();
+
+        TranspositionTable { slots, mask: capacity / 2 - 1, generation: AtomicU64::new(0) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        return self.slots.len();
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Called when a search starts : the entries stored until then may be
+    /// replaced by shallower ones.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The deep slot and the recent slot of a position.
+    fn pair(&self, hash: u64) -> [&Slot; 2] {
+        let index = (hash as usize & self.mask) * 2;
+        return [&self.slots[index], &self.slots[index + 1]];
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        for slot in self.pair(hash) {
+            let data = slot.data.load(Ordering::Relaxed);
+            if data != 0 && slot.key.load(Ordering::Relaxed) ^ data == hash {
+                return Some(unpack(data));
+            }
+        }
+        return None;
+    }
+
+    /// Keeps the deepest result of a position. Another position takes the
+    /// first slot of the pair if it is searched at least as deep as the one
+    /// there, or if that one comes from a previous search ; otherwise it
+    /// takes the second slot.
+    pub fn store(&self, hash: u64, depth: i32, bound: Bound, score: i32, best_move: Option<Move>) {
+        let generation = self.generation.load(Ordering::Relaxed) & GENERATION_MASK;
+        let data = pack(&TtEntry { depth, bound, score, best_move }) | generation << GENERATION_SHIFT;
+        let [deep, recent] = self.pair(hash);
+
+        let deep_data = deep.data.load(Ordering::Relaxed);
+        let deep_hash = deep.key.load(Ordering::Relaxed) ^ deep_data;
+        if deep_data != 0 && deep_hash == hash {
+            let old = unpack(deep_data);
+            if old.depth > depth && bound != Bound::Exact {
+                return;
+            }
+            write(deep, hash, data);
+            return;
+        }
+        let deep_generation = (deep_data >> GENERATION_SHIFT) & GENERATION_MASK;
+        if deep_data == 0 || deep_generation != generation || unpack(deep_data).depth <= depth {
+            write(deep, hash, data);
+            // Only one version of the position.
+            if recent.key.load(Ordering::Relaxed) ^ recent.data.load(Ordering::Relaxed) == hash {
+                write(recent, 0, 0);
+            }
+            return;
+        }
+        write(recent, hash, data);
+    }
+}
+
+fn write(slot: &Slot, hash: u64, data: u64) {
+    slot.key.store(hash ^ data, Ordering::Relaxed);
+    slot.data.store(data, Ordering::Relaxed);
+}
+
+const GENERATION_SHIFT: u32 = 48;
+const GENERATION_MASK: u64 = 0b11_1111;
+
+/* Data layout : score in bits 0..32, depth in 32..40, bound in 40..42
+ * (never 0, so an empty slot is all zeros), move in 42..48, generation of the
+ * search in 48..54. */
+fn pack(entry: &TtEntry) -> u64 {
+    let bound: u64 = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3
+    };
+    let best_move: u64 = match entry.best_move {
+        Some(mv) => 0b10_0000 | (mv.color as u64) << 4 | mv.hole as u64,
+        None => 0
+    };
+
+    return entry.score as u32 as u64
+        | (entry.depth.clamp(0, 255) as u64) << 32
+        | bound << 40
+        | best_move << 42;
+}
+
+fn unpack(data: u64) -> TtEntry {
+    let bound = match (data >> 40) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper
+    };
+    let best_move = (data >> 42) & 0b11_1111;
+    let best_move = if best_move & 0b10_0000 != 0 {
+        Some(Move::new((best_move & 0b1111) as usize, Color::from_integer(((best_move >> 4) & 1) as usize)))
+    } else {
+        None
+    };
+
+    TtEntry {
+        depth: ((data >> 32) & 0xff) as i32,
+        bound,
+        score: data as u32 as i32,
+        best_move
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_probe() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(65536, tt.capacity());
+        assert_eq!(None, tt.probe(42));
+
+        tt.store(42, 7, Bound::Lower, -9_999_995, Some(Move::new(13, Color::Blue)));
+        assert_eq!(
+            Some(TtEntry { depth: 7, bound: Bound::Lower, score: -9_999_995, best_move: Some(Move::new(13, Color::Blue)) }),
+            tt.probe(42)
+        );
+        assert_eq!(None, tt.probe(42 + tt.capacity() as u64));
+
+        tt.store(42, 3, Bound::Upper, 12, None);
+        assert_eq!(7, tt.probe(42).unwrap().depth);
+        tt.store(42, 3, Bound::Exact, 12, Some(Move::new(0, Color::Red)));
+        assert_eq!(
+            Some(TtEntry { depth: 3, bound: Bound::Exact, score: 12, best_move: Some(Move::new(0, Color::Red)) }),
+            tt.probe(42)
+        );
+
+        // Same pair : a shallower position goes to the recent slot, the next
+        // one replaces it.
+        let others = [42 + tt.capacity() as u64, 42 + 2 * tt.capacity() as u64];
+        tt.store(others[0], 1, Bound::Upper, 0, None);
+        assert_eq!(3, tt.probe(42).unwrap().depth);
+        assert_eq!(1, tt.probe(others[0]).unwrap().depth);
+        tt.store(others[1], 2, Bound::Upper, 0, None);
+        assert_eq!(3, tt.probe(42).unwrap().depth);
+        assert_eq!(None, tt.probe(others[0]));
+
+        // A deeper one takes the deep slot, as any one once the search is over.
+        tt.store(others[0], 5, Bound::Lower, 0, None);
+        assert_eq!(None, tt.probe(42));
+        assert_eq!(5, tt.probe(others[0]).unwrap().depth);
+        tt.new_search();
+        tt.store(42, 1, Bound::Exact, 0, None);
+        assert_eq!(1, tt.probe(42).unwrap().depth);
+        assert_eq!(None, tt.probe(others[0]));
+        assert_eq!(2, tt.probe(others[1]).unwrap().depth);
+
+        tt.clear();
+        assert_eq!(None, tt.probe(42));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::dataset::LabeledPosition;
+use crate::eval::*;
+
+/// Bound of the tuned weights : the evaluation of any position stays far
+/// from the scores of the wins.
+pub const MAX_WEIGHT: i32 = 10_000;
+/// Largest move of a weight in one step of the local search.
+const MAX_STEP: i32 = 1024;
+
+/// A dataset position reduced to what the loss needs : the evaluation being
+/// linear in the weights, the features are computed once.
+struct Sample {
+    features: [i32; TERMS],
+    /// Points of the player to move.
+    result: f64,
+}
+
+/// Texel-style tuning : the static evaluation of a position, seen by the
+/// player to move, predicts its chances through a logistic curve, and the
+/// weights are moved one at a time while the prediction improves.
+pub struct Tuner {
+    samples: Vec<Sample>,
+    /// Slope of the logistic curve, evaluation points to log-odds.
+    scale: f64,
+    /// Factor of the sum of the squared weights added to the loss, 0 by
+    /// default. Above 0, the terms which barely help stay small.
+    l2: f64,
+}
+
+impl Tuner {
+    /// Finished positions are left out, the search scores them itself.
+    pub fn new(positions: &[LabeledPosition]) -> Tuner {
+        let samples = positions
+            .iter()
+            .filter_map(|position| {
+                let (board, player) = position.board().ok()?;
+                if board.is_final_position() {
+                    return None;
+                }
+                Some(Sample { features: features(&board, player), result: position.result_for(player) })
+            })
+            .collect();
+        Tuner { samples, scale: 0.01, l2: 0.0 }
+    }
+
+    pub fn len(&self) -> usize {
+        return self.samples.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.samples.is_empty();
+    }
+
+    pub fn scale(&self) -> f64 {
+        return self.scale;
+    }
+
+    pub fn set_l2(&mut self, l2: f64) {
+        self.l2 = l2;
+    }
+
+    /// Mean logistic loss (cross-entropy) of the predictions of `weights`,
+    /// plus the L2 penalty.
+    pub fn loss(&self, weights: &EvalWeights) -> f64 {
+        return self.penalized_loss(&weights.to_array());
+    }
+
+    fn penalized_loss(&self, weights: &[i32; TERMS]) -> f64 {
+        let squares: f64 = weights.iter().map(|&weight| weight as f64 * weight as f64).sum();
+        return loss(&self.samples, weights, self.scale) + self.l2 * squares;
+    }
+
+    /// Fits the slope of the curve to `weights`, which the tuning keeps.
+    pub fn fit_scale(&mut self, weights: &EvalWeights) -> f64 {
+        let weights = weights.to_array();
+        let (mut low, mut high) = (1e-5_f64.ln(), 1.0_f64.ln());
+        // Golden-section search on the log of the slope.
+        let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..60 {
+            let left = high - ratio * (high - low);
+            let right = low + ratio * (high - low);
+            if loss(&self.samples, &weights, left.exp()) < loss(&self.samples, &weights, right.exp()) {
+                high = right;
+            } else {
+                low = left;
+            }
+        }
+        self.scale = ((low + high) / 2.0).exp();
+        return self.scale;
+    }
+
+    /// Local search : each weight is moved by one, then by larger steps while
+    /// it keeps lowering the loss, within `MAX_WEIGHT`. Stops after
+    /// `max_passes` passes over the weights or when a pass changes nothing.
+    /// `on_pass` is told the loss after each pass.
+    pub fn tune(&self, weights: &EvalWeights, max_passes: usize, on_pass: &mut dyn FnMut(usize, f64, &EvalWeights)) -> EvalWeights {
+        let mut values = weights.to_array().map(|weight| weight.clamp(-MAX_WEIGHT, MAX_WEIGHT));
+        let mut best_loss = self.penalized_loss(&values);
+
+        for pass in 1..=max_passes {
+            let mut improved = false;
+            for term in 0..TERMS {
+                for direction in [1, -1] {
+                    let mut step = direction;
+                    loop {
+                        let mut candidate = values;
+                        candidate[term] = candidate[term].saturating_add(step).clamp(-MAX_WEIGHT, MAX_WEIGHT);
+                        if candidate[term] == values[term] {
+                            break;
+                        }
+                        let candidate_loss = self.penalized_loss(&candidate);
+                        if candidate_loss >= best_loss {
+                            break;
+                        }
+                        values = candidate;
+                        best_loss = candidate_loss;
+                        improved = true;
+                        step = step.saturating_mul(2).clamp(-MAX_STEP, MAX_STEP);
+                    }
+                }
+            }
+            on_pass(pass, best_loss, &EvalWeights::from_array(values));
+            if !improved {
+                break;
+            }
+        }
+        return EvalWeights::from_array(values);
+    }
+}
+
+fn loss(samples: &[Sample], weights: &[i32; TERMS], scale: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = samples
+        .iter()
+        .map(|sample| {
+            let eval: i32 = sample.features.iter().zip(weights).map(|(feature, weight)| feature * weight).sum();
+            let probability = (1.0 / (1.0 + (-scale * eval as f64).exp())).clamp(1e-9, 1.0 - 1e-9);
+            -(sample.result * probability.ln() + (1.0 - sample.result) * (1.0 - probability).ln())
+        })
+        .sum();
+    return total / samples.len() as f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{random_moves, Board};
+
+    /// Positions of random games, won by whoever leads in the attics : the
+    /// tuning has to find that the attics matter.
+    fn attic_dataset() -> Vec<LabeledPosition> {
+        let mut positions = Vec::new();
+        for game in 0..40 {
+            let moves = random_moves(game, 60);
+            let mut board = Board::new();
+            for (ply, &mv) in moves.iter().enumerate() {
+                board.make_move(ply % 2, mv);
+                let lead = board.players_attics[0] - board.players_attics[1];
+                let result = if lead > 0 { 1.0 } else if lead < 0 { 0.0 } else { 0.5 };
+                positions.push(LabeledPosition { moves: moves[..=ply].to_vec(), result, score: None });
+            }
+        }
+        return positions;
+    }
+
+    #[test]
+    fn test_tune() {
+        let positions = attic_dataset();
+        let mut tuner = Tuner::new(&positions);
+        assert!(!tuner.is_empty());
+
+        let start = EvalWeights::from_array([0; TERMS]);
+        let flat_loss = tuner.loss(&start);
+        assert!((flat_loss - 2.0_f64.ln()).abs() < 1e-9);
+
+        tuner.fit_scale(&EvalWeights::default());
+        let mut passes = Vec::new();
+        let tuned = tuner.tune(&start, 20, &mut |pass, loss, _| passes.push((pass, loss)));
+
+        assert!(tuned.attic_difference > 0);
+        assert!(tuner.loss(&tuned) < flat_loss);
+        assert_eq!(tuner.loss(&tuned), passes.last().unwrap().1);
+        assert!(passes.windows(2).all(|pair| pair[1].1 <= pair[0].1));
+    }
+
+    #[test]
+    fn test_tune_bounds() {
+        let positions = attic_dataset();
+        let mut tuner = Tuner::new(&positions);
+        tuner.fit_scale(&EvalWeights::default());
+        let squares = |weights: &EvalWeights| weights.to_array().iter().map(|&weight| weight as i64 * weight as i64).sum::<i64>();
+
+        // The weights out of range are brought back, and stay there.
+        let start = EvalWeights { attic_difference: i32::MAX, mobility: i32::MIN, ..EvalWeights::default() };
+        let tuned = tuner.tune(&start, 20, &mut |_, _, _| ());
+        assert!(tuned.to_array().iter().all(|weight| weight.abs() <= MAX_WEIGHT));
+
+        // The penalty keeps the weights smaller.
+        let free = tuner.tune(&EvalWeights::default(), 20, &mut |_, _, _| ());
+        tuner.set_l2(1e-3);
+        let penalized = tuner.tune(&EvalWeights::default(), 20, &mut |_, _, _| ());
+        assert!(squares(&penalized) < squares(&free));
+        assert!(tuner.loss(&penalized) < tuner.loss(&free));
+    }
+}
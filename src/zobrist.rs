@@ -0,0 +1,74 @@
+//! Zobrist keys of the positions, generated at compile time.
+
+/// Seed counts are indexed directly : no hole nor attic can hold more than
+/// the 64 seeds of the game.
+const MAX_SEEDS: usize = 64;
+
+struct Keys {
+    red_holes: [[u64; MAX_SEEDS + 1]; 16],
+    blue_holes: [[u64; MAX_SEEDS + 1]; 16],
+    players_attics: [[u64; MAX_SEEDS + 1]; 2],
+    side_to_move: u64,
+    searching_player: u64
+}
+
+const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    return z ^ (z >> 31);
+}
+
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        red_holes: [[0; MAX_SEEDS + 1]; 16],
+        blue_holes: [[0; MAX_SEEDS + 1]; 16],
+        players_attics: [[0; MAX_SEEDS + 1]; 2],
+        side_to_move: 0,
+        searching_player: 0
+    };
+    let mut state = 0x0a7e_0a7e_0a7e_0a7e;
+
+    let mut seeds = 0;
+    while seeds <= MAX_SEEDS {
+        let mut hole = 0;
+        while hole < 16 {
+            keys.red_holes[hole][seeds] = splitmix64(&mut state);
+            keys.blue_holes[hole][seeds] = splitmix64(&mut state);
+            hole += 1;
+        }
+        keys.players_attics[0][seeds] = splitmix64(&mut state);
+        keys.players_attics[1][seeds] = splitmix64(&mut state);
+        seeds += 1;
+    }
+    keys.side_to_move = splitmix64(&mut state);
+    keys.searching_player = splitmix64(&mut state);
+
+    return keys;
+}
+
+static KEYS: Keys = generate_keys();
+
+pub(crate) fn red_hole(hole: usize, seeds: i32) -> u64 {
+    return KEYS.red_holes[hole][seeds as usize];
+}
+
+pub(crate) fn blue_hole(hole: usize, seeds: i32) -> u64 {
+    return KEYS.blue_holes[hole][seeds as usize];
+}
+
+pub(crate) fn player_attic(player: usize, seeds: i32) -> u64 {
+    return KEYS.players_attics[player][seeds as usize];
+}
+
+/// Xored in when the second player is to move.
+pub(crate) fn side_to_move(player: usize) -> u64 {
+    return if player == 1 { KEYS.side_to_move } else { 0 };
+}
+
+/// Xored into the table keys of the searches for the second player : their
+/// scores do not hold for the first one.
+pub(crate) fn searching_player(player: usize) -> u64 {
+    return if player == 1 { KEYS.searching_player } else { 0 };
+}